@@ -0,0 +1,259 @@
+#![allow(dead_code)]
+//! Unsigned integers of any size, for counts that outgrow machine words.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+/// Base 2^32 digits, least significant first, without trailing zeros.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self { limbs: vec![] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalized(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        self
+    }
+
+    /// Divides in place by `d`, returning the remainder.
+    fn div_rem_small(&mut self, d: u32) -> u32 {
+        let mut rem = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let cur = (rem << 32) | *limb as u64;
+            *limb = (cur / d as u64) as u32;
+            rem = cur % d as u64;
+        }
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        rem as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        Self {
+            limbs: vec![n as u32, (n >> 32) as u32],
+        }
+        .normalized()
+    }
+}
+
+impl From<usize> for BigUint {
+    fn from(n: usize) -> Self {
+        Self::from(n as u64)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+        let mut carry = 0u64;
+        for (i, limb) in self.limbs.iter_mut().enumerate() {
+            let sum = *limb as u64 + other.limbs.get(i).copied().unwrap_or(0) as u64 + carry;
+            *limb = sum as u32;
+            carry = sum >> 32;
+            if carry == 0 && i >= other.limbs.len() {
+                break;
+            }
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+    fn add(mut self, other: BigUint) -> BigUint {
+        self += &other;
+        self
+    }
+}
+
+impl Sub for BigUint {
+    type Output = BigUint;
+    /// Panics if `other` is larger, like subtraction of machine integers.
+    fn sub(mut self, other: BigUint) -> BigUint {
+        assert!(self >= other, "attempt to subtract with overflow");
+        let mut borrow = 0i64;
+        for (i, limb) in self.limbs.iter_mut().enumerate() {
+            let diff = *limb as i64 - other.limbs.get(i).copied().unwrap_or(0) as i64 - borrow;
+            *limb = diff.rem_euclid(1 << 32) as u32;
+            borrow = (diff < 0) as i64;
+        }
+        self.normalized()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+    fn mul(self, other: BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let cur = limbs[i + j] as u64 + a as u64 * b as u64 + carry;
+                limbs[i + j] = cur as u32;
+                carry = cur >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        Self { limbs }.normalized()
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.pad_integral(true, "", "0");
+        }
+        // Peel off nine decimal digits at a time.
+        let mut n = self.clone();
+        let mut chunks = vec![];
+        while !n.is_zero() {
+            chunks.push(n.div_rem_small(1_000_000_000));
+        }
+        let mut s = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            s.push_str(&format!("{:09}", chunk));
+        }
+        f.pad_integral(true, "", &s)
+    }
+}
+
+//------------------------------------------------------------------
+
+/// The numbers a simulation counts with, so the same code can use machine
+/// integers when they are known to suffice and `BigUint` when not.
+pub trait Count:
+    Clone
+    + Ord
+    + fmt::Debug
+    + fmt::Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+{
+    fn zero() -> Self;
+    fn from_usize(n: usize) -> Self;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::zero()
+    }
+}
+
+macro_rules! impl_count {
+    ($($t:ty),*) => {
+        $(
+            impl Count for $t {
+                fn zero() -> Self {
+                    0
+                }
+                fn from_usize(n: usize) -> Self {
+                    n as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_count!(usize, u64, u128);
+
+impl Count for BigUint {
+    fn zero() -> Self {
+        BigUint::zero()
+    }
+    fn from_usize(n: usize) -> Self {
+        BigUint::from(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(n: u128) -> BigUint {
+        BigUint::from((n >> 64) as u64) * BigUint::from(1u64 << 32) * BigUint::from(1u64 << 32)
+            + BigUint::from(n as u64)
+    }
+
+    #[test]
+    fn test_matches_u128() {
+        let values: [u128; 6] = [
+            0,
+            1,
+            9,
+            1 << 32,
+            u64::MAX as u128 + 7,
+            123456789012345678901234567,
+        ];
+        for &a in values.iter() {
+            assert_eq!(big(a).to_string(), a.to_string());
+            for &b in values.iter() {
+                assert_eq!(big(a) + big(b), big(a + b));
+                assert_eq!(big(a).cmp(&big(b)), a.cmp(&b));
+                if a >= b {
+                    assert_eq!(big(a) - big(b), big(a - b));
+                }
+                if let Some(p) = a.checked_mul(b) {
+                    assert_eq!(big(a) * big(b), big(p));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_large() {
+        // 2^128 is one past u128::MAX.
+        let two_128 = big(1 << 64) * big(1 << 64);
+        assert_eq!(
+            two_128.to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        assert_eq!(two_128.clone() - big(1), big(u128::MAX));
+        assert!(two_128 > big(u128::MAX));
+
+        let mut factorial = BigUint::from(1u64);
+        for i in 1..=30u64 {
+            factorial = factorial * BigUint::from(i);
+        }
+        assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+        assert_eq!(format!("{:>5}", BigUint::zero()), "    0");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sub_overflow() {
+        let _ = big(1) - big(2);
+    }
+}
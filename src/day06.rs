@@ -1,50 +1,40 @@
+#![allow(dead_code)]
 use super::ReadStr;
+use crate::bigint::Count;
 use regex::Regex;
-use std::fs;
 
+/// Number of fish per timer value.
 #[derive(Debug, Clone, PartialEq)]
-struct State([usize; 9]);
+struct State<C: Count = usize>([C; 9]);
 
-impl State {
+impl<C: Count> State<C> {
     fn step(self) -> Self {
-        let mut new_state = State([0; 9]);
-
-        new_state.0[0] = self.0[1];
-        new_state.0[1] = self.0[2];
-        new_state.0[2] = self.0[3];
-        new_state.0[3] = self.0[4];
-        new_state.0[4] = self.0[5];
-        new_state.0[5] = self.0[6];
-        new_state.0[6] = self.0[7] + self.0[0];
-        new_state.0[7] = self.0[8];
-        new_state.0[8] = self.0[0];
-
-        new_state
+        let [t0, t1, t2, t3, t4, t5, t6, t7, t8] = self.0;
+        State([t1, t2, t3, t4, t5, t6, t7 + t0.clone(), t8, t0])
     }
 
-    fn sum(&self) -> usize {
-        self.0.iter().sum()
+    fn sum(&self) -> C {
+        self.0.iter().cloned().fold(C::zero(), |acc, n| acc + n)
     }
 }
 
-impl ReadStr for State {
+impl<C: Count> ReadStr for State<C> {
     type Err = ();
     fn read_str(s: &str) -> Result<Self, Self::Err> {
         let re = Regex::new(r"\d+").unwrap();
-        let mut state = State([0; 9]);
+        let mut counts = [0usize; 9];
         for cap in re.captures_iter(s) {
-            let idx = match usize::from_str_radix(&cap[0], 10) {
-                Ok(idx) => idx,
-                Err(_) => return Err(()),
-            };
-            state.0[idx] += 1;
+            let idx = cap[0].parse::<usize>().map_err(|_| ())?;
+            *counts.get_mut(idx).ok_or(())? += 1;
         }
-        return Ok(state);
+        Ok(State(counts.map(C::from_usize)))
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::bigint::BigUint;
+    use std::fs;
 
     const INPUT_TEST: &str = "input/day06_test.txt";
     const INPUT: &str = "input/day06.txt";
@@ -61,14 +51,14 @@ mod test {
     #[test]
     fn test_read() {
         let data = load_test_data();
-        let s = State::read_str(&data).unwrap();
+        let s: State = State::read_str(&data).unwrap();
 
         print!("{:?}\n", s);
     }
     #[test]
     fn test_part_1() {
         let data = load_test_data();
-        let mut s = State::read_str(&data).unwrap();
+        let mut s: State = State::read_str(&data).unwrap();
 
         for _ in 0..80 {
             s = s.step();
@@ -80,7 +70,7 @@ mod test {
     #[test]
     fn part_1() {
         let data = load_data();
-        let mut s = State::read_str(&data).unwrap();
+        let mut s: State = State::read_str(&data).unwrap();
 
         for _ in 0..80 {
             s = s.step();
@@ -92,7 +82,7 @@ mod test {
     #[test]
     fn part_2() {
         let data = load_data();
-        let mut s = State::read_str(&data).unwrap();
+        let mut s: State = State::read_str(&data).unwrap();
 
         for _ in 0..256 {
             s = s.step();
@@ -100,4 +90,39 @@ mod test {
 
         print!("{}, {:?}\n", s.sum(), s);
     }
+
+    #[test]
+    fn test_part_1_answer() {
+        let data = load_test_data();
+        let mut s: State = State::read_str(&data).unwrap();
+
+        for _ in 0..80 {
+            s = s.step();
+        }
+
+        assert_eq!(s.sum(), 5934);
+    }
+
+    #[test]
+    fn test_big_counts() {
+        let data = load_test_data();
+        let mut small: State<u128> = State::read_str(&data).unwrap();
+        let mut big: State<BigUint> = State::read_str(&data).unwrap();
+
+        for day in 1..=600 {
+            small = small.step();
+            big = big.step();
+            if day == 256 {
+                assert_eq!(big.sum().to_string(), "26984457539");
+            }
+        }
+        // u128 still holds the count after 600 days.
+        assert_eq!(big.sum().to_string(), small.sum().to_string());
+
+        for _ in 0..2000 {
+            big = big.step();
+        }
+        let u64_max = BigUint::from(u64::MAX);
+        assert!(big.sum() > u64_max.clone() * u64_max.clone() * u64_max);
+    }
 }
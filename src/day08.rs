@@ -1,12 +1,13 @@
 #![allow(dead_code, unused)]
 
+use std::error::Error;
 use std::fmt;
 use std::str;
 
 const VALID_NUMBERS: [u8; 10] = [
     //76543210
     0b01110111u8, // 0
-    0b00100010u8, // 1
+    0b00100100u8, // 1
     0b01011101u8, // 2
     0b01101101u8, // 3
     0b00101110u8, // 4
@@ -14,9 +15,134 @@ const VALID_NUMBERS: [u8; 10] = [
     0b01111011u8, // 6
     0b00100101u8, // 7
     0b01111111u8, // 8
-    0b01101111u8,
+    0b01101111u8, // 9
 ];
 
+const NUM_SEGMENTS: usize = 7;
+const NUM_INPUTS: usize = 10;
+const NUM_OUTPUTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Input,
+    Output,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Input => write!(f, "input"),
+            Side::Output => write!(f, "output"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DisplayError {
+    MissingSeparator,
+    InvalidPattern {
+        side: Side,
+        index: usize,
+        pattern: String,
+        reason: String,
+    },
+    DuplicatePattern {
+        pattern: String,
+        first: usize,
+        second: usize,
+    },
+    WrongCount {
+        side: Side,
+        expected: usize,
+        found: usize,
+    },
+    /// No symbol lights as many segments as `pattern`.
+    UnmatchedLength {
+        pattern: String,
+        lit: u32,
+    },
+    /// The last pattern cannot be wired consistently with the ones before it.
+    NoWiring {
+        patterns: Vec<String>,
+    },
+    Ambiguous {
+        count: usize,
+    },
+    /// Wirings that allow for a faulty segment read different values.
+    AmbiguousValue {
+        values: Vec<usize>,
+    },
+    UnknownOutput {
+        index: usize,
+        pattern: String,
+    },
+    Line {
+        line: usize,
+        error: Box<DisplayError>,
+    },
+}
+
+impl fmt::Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeparator => write!(f, "missing ' | ' between input and output"),
+            Self::InvalidPattern {
+                side,
+                index,
+                pattern,
+                reason,
+            } => write!(f, "{} pattern #{} `{}`: {}", side, index, pattern, reason),
+            Self::DuplicatePattern {
+                pattern,
+                first,
+                second,
+            } => write!(
+                f,
+                "input patterns #{} and #{} both light `{}`",
+                first, second, pattern
+            ),
+            Self::WrongCount {
+                side,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} {} patterns, found {}",
+                expected, side, found
+            ),
+            Self::UnmatchedLength { pattern, lit } => write!(
+                f,
+                "input pattern `{}` lights {} segments, but no digit does",
+                pattern, lit
+            ),
+            Self::NoWiring { patterns } => {
+                let (culprit, others) = patterns.split_last().ok_or(fmt::Error)?;
+                write!(f, "no wiring shows a digit for `{}`", culprit)?;
+                if !others.is_empty() {
+                    let others: Vec<String> = others.iter().map(|p| format!("`{}`", p)).collect();
+                    write!(f, " together with {}", others.join(", "))?;
+                }
+                Ok(())
+            }
+            Self::Ambiguous { count } => {
+                write!(f, "{} different wirings fit the input patterns", count)
+            }
+            Self::AmbiguousValue { values } => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "the outputs read as any of {}", values.join(", "))
+            }
+            Self::UnknownOutput { index, pattern } => write!(
+                f,
+                "output pattern #{} `{}` does not show a digit",
+                index, pattern
+            ),
+            Self::Line { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl Error for DisplayError {}
+
 #[derive(Debug)]
 struct Digit(u8);
 
@@ -77,10 +203,231 @@ impl str::FromStr for Digit {
     }
 }
 
+/// A scrambled pattern of lit wires, bit `i` corresponds to wire `'a' + i`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Wires(u32);
+
+impl Wires {
+    /// Parse a pattern for a display with `num_segments` wires. The error
+    /// says what is wrong with the pattern.
+    fn from_letters(s: &str, num_segments: usize) -> Result<Self, String> {
+        let mut bits = 0;
+        for c in s.trim().chars() {
+            if !c.is_ascii_lowercase() || (c as u8 - b'a') as usize >= num_segments {
+                return Err(format!("'{}' is not one of the {} wires", c, num_segments));
+            }
+            let bit = 1 << (c as u8 - b'a');
+            if bits & bit != 0 {
+                return Err(format!("wire '{}' is given twice", c));
+            }
+            bits |= bit;
+        }
+        Ok(Wires(bits))
+    }
+
+    fn has(&self, wire: usize) -> bool {
+        (self.0 >> wire) & 1 == 1
+    }
+
+    /// Route every lit wire to the segment given by `wiring`.
+    fn rewire(&self, wiring: &[usize]) -> u32 {
+        let mut cfg = 0;
+        for (wire, segment) in wiring.iter().enumerate() {
+            cfg |= ((self.0 >> wire) & 1) << segment;
+        }
+        cfg
+    }
+}
+
+impl fmt::Display for Wires {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for wire in 0..32 {
+            if self.has(wire) {
+                write!(f, "{}", (b'a' + wire as u8) as char)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl str::FromStr for Wires {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Wires::from_letters(s, NUM_SEGMENTS)
+    }
+}
+
+/// A set of symbols a segment display can show, each given as the bitmask of
+/// its lit segments. Up to 32 segments are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Alphabet {
+    num_segments: usize,
+    symbols: Vec<u32>,
+}
+
+impl Alphabet {
+    fn new(num_segments: usize, symbols: Vec<u32>) -> Self {
+        assert!(num_segments <= 32, "At most 32 segments are supported.");
+        Self {
+            num_segments,
+            symbols,
+        }
+    }
+
+    fn seven_segment() -> Self {
+        Self::new(
+            NUM_SEGMENTS,
+            VALID_NUMBERS.iter().map(|&vn| vn as u32).collect(),
+        )
+    }
+
+    /// Index of the symbol showing exactly the segments in `cfg`.
+    fn symbol(&self, cfg: u32) -> Option<usize> {
+        self.symbols.iter().position(|&sym| sym == cfg)
+    }
+
+    /// All wirings under which every pattern shows a symbol of the alphabet.
+    /// `wiring[w]` is the segment driven by wire `w`. More than one result
+    /// means the patterns are ambiguous.
+    fn wirings(&self, patterns: &[Wires]) -> Vec<Vec<usize>> {
+        self.search(patterns, None)
+    }
+
+    /// Like `wirings`, but the signal on wire `faulty` is not trusted, so the
+    /// patterns only have to agree with a symbol on the remaining segments.
+    fn wirings_with_fault(&self, patterns: &[Wires], faulty: usize) -> Vec<Vec<usize>> {
+        self.search(patterns, Some(faulty))
+    }
+
+    fn search(&self, patterns: &[Wires], faulty: Option<usize>) -> Vec<Vec<usize>> {
+        let mut found = vec![];
+        if patterns
+            .iter()
+            .any(|p| p.0.checked_shr(self.num_segments as u32).unwrap_or(0) != 0)
+        {
+            return found;
+        }
+
+        // Every pattern starts out with all symbols lighting as many segments,
+        // or one more if the faulty wire may have been dropped.
+        let candidates: Vec<Vec<u32>> = patterns
+            .iter()
+            .map(|p| {
+                let lit = match faulty {
+                    Some(wire) => (p.0 & !(1 << wire)).count_ones(),
+                    None => p.0.count_ones(),
+                };
+                let extra = faulty.is_some() as u32;
+                self.symbols
+                    .iter()
+                    .copied()
+                    .filter(|sym| (lit..=lit + extra).contains(&sym.count_ones()))
+                    .collect()
+            })
+            .collect();
+
+        let mut wiring = Vec::with_capacity(self.num_segments);
+        self.assign(patterns, candidates, faulty, &mut wiring, &mut found);
+        found
+    }
+
+    /// Indices of a minimal set of patterns which no wiring satisfies at
+    /// once, with the pattern that breaks the set last. Empty if all
+    /// patterns can be wired.
+    fn conflict(&self, patterns: &[Wires]) -> Vec<usize> {
+        let mut core: Vec<usize> = vec![];
+        for (i, _) in patterns.iter().enumerate() {
+            core.push(i);
+            if self.wirings(&self.select(patterns, &core)).is_empty() {
+                break;
+            }
+        }
+        if !self.wirings(&self.select(patterns, &core)).is_empty() {
+            return vec![];
+        }
+
+        // Drop every earlier pattern the conflict does not depend on.
+        let mut i = 0;
+        while i + 1 < core.len() {
+            let mut smaller = core.clone();
+            smaller.remove(i);
+            if self.wirings(&self.select(patterns, &smaller)).is_empty() {
+                core = smaller;
+            } else {
+                i += 1;
+            }
+        }
+        core
+    }
+
+    fn select(&self, patterns: &[Wires], indices: &[usize]) -> Vec<Wires> {
+        indices.iter().map(|&i| patterns[i]).collect()
+    }
+
+    /// Index of the symbol closest to `cfg` in Hamming distance, unless
+    /// several symbols are equally close.
+    fn nearest(&self, cfg: u32) -> Option<usize> {
+        let distance = |sym: &u32| (sym ^ cfg).count_ones();
+        let best = self.symbols.iter().map(distance).min()?;
+        let mut closest = (0..self.symbols.len()).filter(|&i| distance(&self.symbols[i]) == best);
+        match (closest.next(), closest.next()) {
+            (Some(i), None) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Assign the next wire to every free segment, dropping the candidate
+    /// symbols that disagree with the pattern on that wire.
+    fn assign(
+        &self,
+        patterns: &[Wires],
+        candidates: Vec<Vec<u32>>,
+        faulty: Option<usize>,
+        wiring: &mut Vec<usize>,
+        found: &mut Vec<Vec<usize>>,
+    ) {
+        let wire = wiring.len();
+        if wire == self.num_segments {
+            found.push(wiring.clone());
+            return;
+        }
+
+        for segment in 0..self.num_segments {
+            if wiring.contains(&segment) {
+                continue;
+            }
+
+            let mut next = Vec::with_capacity(candidates.len());
+            for (pattern, syms) in patterns.iter().zip(candidates.iter()) {
+                let lit = pattern.has(wire);
+                let syms: Vec<u32> = syms
+                    .iter()
+                    .copied()
+                    .filter(|sym| faulty == Some(wire) || ((sym >> segment) & 1 == 1) == lit)
+                    .collect();
+                if syms.is_empty() {
+                    break;
+                }
+                next.push(syms);
+            }
+            if next.len() < candidates.len() {
+                continue;
+            }
+
+            wiring.push(segment);
+            self.assign(patterns, next, faulty, wiring, found);
+            wiring.pop();
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Display {
     input: Vec<Option<Digit>>,
     output: Vec<Option<Digit>>,
+    input_wires: Vec<Wires>,
+    output_wires: Vec<Wires>,
 }
 
 impl Display {
@@ -93,6 +440,119 @@ impl Display {
         }
         acc
     }
+
+    /// Find the wiring which maps every input pattern onto a valid number.
+    /// `wiring[w]` is the segment driven by wire `w`.
+    fn deduce_wiring(&self) -> Result<Vec<usize>, DisplayError> {
+        let alphabet = Alphabet::seven_segment();
+        let mut wirings = alphabet.wirings(&self.input_wires);
+        match wirings.len() {
+            0 => Err(self.explain(&alphabet)),
+            1 => Ok(wirings.remove(0)),
+            count => Err(DisplayError::Ambiguous { count }),
+        }
+    }
+
+    /// Say why no wiring fits the input patterns.
+    fn explain(&self, alphabet: &Alphabet) -> DisplayError {
+        for wires in self.input_wires.iter() {
+            let lit = wires.0.count_ones();
+            if !alphabet.symbols.iter().any(|sym| sym.count_ones() == lit) {
+                let pattern = wires.to_string();
+                return DisplayError::UnmatchedLength { pattern, lit };
+            }
+        }
+        let patterns = alphabet
+            .conflict(&self.input_wires)
+            .into_iter()
+            .map(|i| self.input_wires[i].to_string())
+            .collect();
+        DisplayError::NoWiring { patterns }
+    }
+
+    /// Decode the output digits and read them as a single decimal number.
+    fn decode(&self) -> Result<usize, DisplayError> {
+        let wiring = self.deduce_wiring()?;
+        let mut value = 0;
+        for (index, wires) in self.output_wires.iter().enumerate() {
+            let digit = Digit::from_cfg(wires.rewire(&wiring) as u8).map_err(|_| {
+                DisplayError::UnknownOutput {
+                    index,
+                    pattern: wires.to_string(),
+                }
+            })?;
+            value = 10 * value + digit.0 as usize;
+        }
+        Ok(value)
+    }
+
+    /// Decode a display on which one segment may be faulty. Every wiring that
+    /// fits the input patterns apart from a single wire is tried, and each
+    /// output pattern is read as the nearest digit. Fails if the candidate
+    /// wirings disagree on the value.
+    fn decode_tolerant(&self) -> Result<usize, DisplayError> {
+        let err = match self.decode() {
+            Ok(value) => return Ok(value),
+            Err(err @ DisplayError::Ambiguous { .. }) => return Err(err),
+            Err(err) => err,
+        };
+
+        let alphabet = Alphabet::seven_segment();
+        let mut values = vec![];
+        for faulty in 0..NUM_SEGMENTS {
+            for wiring in alphabet.wirings_with_fault(&self.input_wires, faulty) {
+                let digits: Option<Vec<usize>> = self
+                    .output_wires
+                    .iter()
+                    .map(|wires| alphabet.nearest(wires.rewire(&wiring)))
+                    .collect();
+                if let Some(digits) = digits {
+                    let value = digits.iter().fold(0, |acc, d| 10 * acc + d);
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+            }
+        }
+
+        match values.len() {
+            0 => Err(err),
+            1 => Ok(values[0]),
+            _ => {
+                values.sort_unstable();
+                Err(DisplayError::AmbiguousValue { values })
+            }
+        }
+    }
+
+    /// Draw the patterns as they would light a display wired straight
+    /// through, i.e. wire `'a' + i` driving segment `i`.
+    fn render_scrambled(&self) -> String {
+        let cfgs = |wires: &[Wires]| wires.iter().map(|w| w.0 as u8).collect::<Vec<u8>>();
+        render_halves(&cfgs(&self.input_wires), &cfgs(&self.output_wires))
+    }
+
+    /// Draw the decoded input and output digits.
+    fn render_decoded(&self) -> Result<String, DisplayError> {
+        let wiring = self.deduce_wiring()?;
+        let decode = |wires: &[Wires]| -> Result<Vec<u8>, DisplayError> {
+            let mut cfgs = Vec::with_capacity(wires.len());
+            for (index, w) in wires.iter().enumerate() {
+                let digit = Digit::from_cfg(w.rewire(&wiring) as u8).map_err(|_| {
+                    DisplayError::UnknownOutput {
+                        index,
+                        pattern: w.to_string(),
+                    }
+                })?;
+                cfgs.push(VALID_NUMBERS[digit.0 as usize]);
+            }
+            Ok(cfgs)
+        };
+        Ok(render_halves(
+            &decode(&self.input_wires)?,
+            &decode(&self.output_wires)?,
+        ))
+    }
 }
 
 impl fmt::Display for Display {
@@ -117,24 +577,60 @@ impl fmt::Display for Display {
 }
 
 impl str::FromStr for Display {
-    type Err = ();
+    type Err = DisplayError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (input_str, output_str) = s.split_once(" | ").ok_or(())?;
-
-        let mut input = Vec::with_capacity(16);
-        for dstr in input_str.split_whitespace() {
-            let d: Option<Digit> = dstr.parse().ok();
-            input.push(d)
+        let (input_str, output_str) = s.split_once(" | ").ok_or(DisplayError::MissingSeparator)?;
+
+        let (input, input_wires) = parse_patterns(input_str, Side::Input, NUM_INPUTS)?;
+        let (output, output_wires) = parse_patterns(output_str, Side::Output, NUM_OUTPUTS)?;
+
+        for (second, wires) in input_wires.iter().enumerate() {
+            if let Some(first) = input_wires[..second].iter().position(|w| w == wires) {
+                return Err(DisplayError::DuplicatePattern {
+                    pattern: wires.to_string(),
+                    first,
+                    second,
+                });
+            }
         }
 
-        let mut output = Vec::with_capacity(16);
-        for dstr in output_str.split_whitespace() {
-            let d: Option<Digit> = dstr.parse().ok();
-            output.push(d)
-        }
+        Ok(Display {
+            input,
+            output,
+            input_wires,
+            output_wires,
+        })
+    }
+}
 
-        Ok(Display { input, output })
+fn parse_patterns(
+    s: &str,
+    side: Side,
+    expected: usize,
+) -> Result<(Vec<Option<Digit>>, Vec<Wires>), DisplayError> {
+    let mut digits = Vec::with_capacity(16);
+    let mut wires = Vec::with_capacity(16);
+    for (index, dstr) in s.split_whitespace().enumerate() {
+        let d: Option<Digit> = dstr.parse().ok();
+        digits.push(d);
+        wires.push(
+            dstr.parse()
+                .map_err(|reason| DisplayError::InvalidPattern {
+                    side,
+                    index,
+                    pattern: dstr.to_string(),
+                    reason,
+                })?,
+        );
     }
+    if wires.len() != expected {
+        return Err(DisplayError::WrongCount {
+            side,
+            expected,
+            found: wires.len(),
+        });
+    }
+    Ok((digits, wires))
 }
 
 struct Problem {
@@ -149,14 +645,31 @@ impl Problem {
         }
         acc
     }
+
+    fn sum_output_values(&self) -> Result<usize, DisplayError> {
+        let mut acc = 0;
+        for (line, disp) in self.displays.iter().enumerate() {
+            acc += disp.decode().map_err(|error| DisplayError::Line {
+                line: line + 1,
+                error: Box::new(error),
+            })?;
+        }
+        Ok(acc)
+    }
 }
 
 impl str::FromStr for Problem {
-    type Err = ();
+    type Err = DisplayError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut displays = Vec::with_capacity(200);
-        for line in s.lines() {
-            displays.push(line.parse::<Display>()?)
+        for (i, line) in s.lines().enumerate() {
+            displays.push(
+                line.parse::<Display>()
+                    .map_err(|error| DisplayError::Line {
+                        line: i + 1,
+                        error: Box::new(error),
+                    })?,
+            )
         }
         Ok(Problem { displays })
     }
@@ -171,6 +684,37 @@ impl fmt::Display for Problem {
     }
 }
 
+/// Draw segment configurations side by side as three rows of ASCII art:
+///
+/// ```text
+///  _     _  _     _  _  _  _  _
+/// | |  | _| _||_||_ |_   ||_||_|
+/// |_|  ||_  _|  | _||_|  ||_| _|
+/// ```
+fn render(cfgs: &[u8]) -> [String; 3] {
+    let mut rows = [String::new(), String::new(), String::new()];
+    for cfg in cfgs.iter() {
+        let seg = |i: u8, c: char| if get(*cfg, i) == 1 { c } else { ' ' };
+        rows[0].extend([' ', seg(0, '_'), ' ']);
+        rows[1].extend([seg(1, '|'), seg(3, '_'), seg(2, '|')]);
+        rows[2].extend([seg(4, '|'), seg(6, '_'), seg(5, '|')]);
+    }
+    rows
+}
+
+/// Draw the input and output digits of a display, separated by a colon.
+fn render_halves(input: &[u8], output: &[u8]) -> String {
+    let input = render(input);
+    let output = render(output);
+    let separators = ["   ", " : ", "   "];
+    let mut art = String::new();
+    for ((i, o), sep) in input.iter().zip(output.iter()).zip(separators) {
+        art.push_str(format!("{}{}{}", i, sep, o).trim_end());
+        art.push('\n');
+    }
+    art
+}
+
 fn is_valid(cfg: u8) -> bool {
     for vn in VALID_NUMBERS {
         if cfg == vn {
@@ -244,4 +788,213 @@ mod tests {
         let p: Problem = data.parse().unwrap();
         println!("{}, Count Parsed: {}", p, p.count_parsed_output())
     }
+
+    #[test]
+    fn decode_display() {
+        let data =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf";
+        let disp: Display = data.parse().unwrap();
+        assert_eq!(disp.decode(), Ok(5353));
+    }
+
+    #[test]
+    fn test_solve_2() {
+        let data = fs::read_to_string(TEST_INPUT).unwrap();
+        let p: Problem = data.parse().unwrap();
+        assert_eq!(p.sum_output_values(), Ok(61229));
+    }
+
+    const EXAMPLE: &str =
+        "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf";
+
+    #[test]
+    fn parse_errors() {
+        let err = "acedgfb cdfbe".parse::<Display>().unwrap_err();
+        assert_eq!(err, DisplayError::MissingSeparator);
+
+        let data = EXAMPLE.replace("dab", "dah");
+        let err = data.parse::<Display>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "input pattern #4 `dah`: 'h' is not one of the 7 wires"
+        );
+
+        let data = EXAMPLE.replace("dab", "dad");
+        let err = data.parse::<Display>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "input pattern #4 `dad`: wire 'd' is given twice"
+        );
+
+        let data = EXAMPLE.replace("dab", "ba");
+        let err = data.parse::<Display>().unwrap_err();
+        assert_eq!(
+            err,
+            DisplayError::DuplicatePattern {
+                pattern: "ab".to_string(),
+                first: 4,
+                second: 9
+            }
+        );
+
+        let data = EXAMPLE.replace(" cdbaf", "");
+        let err = data.parse::<Display>().unwrap_err();
+        assert_eq!(
+            err,
+            DisplayError::WrongCount {
+                side: Side::Output,
+                expected: 4,
+                found: 3
+            }
+        );
+
+        let data = format!("{}\n{}", EXAMPLE, EXAMPLE.replace(" | ", " "));
+        let err = data.parse::<Problem>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "line 2: missing ' | ' between input and output"
+        );
+    }
+
+    #[test]
+    fn explain_no_wiring() {
+        // Only 7 lights three segments, so `afb` and `dab` cannot both show it.
+        let data = EXAMPLE.replace("eafb", "afb");
+        let disp: Display = data.parse().unwrap();
+        let err = disp.decode().unwrap_err();
+        assert_eq!(
+            err,
+            DisplayError::NoWiring {
+                patterns: vec!["abd".to_string(), "abf".to_string()]
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "no wiring shows a digit for `abf` together with `abd`"
+        );
+
+        let data = EXAMPLE.replace("acedgfb", "a");
+        let disp: Display = data.parse().unwrap();
+        let err = disp.decode().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "input pattern `a` lights 1 segments, but no digit does"
+        );
+    }
+
+    #[test]
+    fn decode_faulty_segment() {
+        // Wire `g` failed to light while showing 0.
+        let data = EXAMPLE.replace("cagedb", "caedb");
+        let disp: Display = data.parse().unwrap();
+        assert!(disp.decode().is_err());
+        assert_eq!(disp.decode_tolerant(), Ok(5353));
+
+        // A segment missing from an output is read as the nearest digit.
+        let data = data.replace("| cdfeb", "| cdfe");
+        let disp: Display = data.parse().unwrap();
+        assert_eq!(disp.decode_tolerant(), Ok(5353));
+
+        // With `e` missing from 8 and 4, wirings read different values.
+        let data = EXAMPLE.replace("acedgfb", "acdgfb").replace("eafb", "afb");
+        let disp: Display = data.parse().unwrap();
+        let err = disp.decode_tolerant().unwrap_err();
+        assert_eq!(
+            err,
+            DisplayError::AmbiguousValue {
+                values: vec![2323, 5353]
+            }
+        );
+        assert_eq!(err.to_string(), "the outputs read as any of 2323, 5353");
+    }
+
+    #[test]
+    fn render_digits() {
+        let [top, middle, bottom] = render(&VALID_NUMBERS);
+        assert_eq!(top, " _     _  _     _  _  _  _  _ ");
+        assert_eq!(middle, "| |  | _| _||_||_ |_   ||_||_|");
+        assert_eq!(bottom, "|_|  ||_  _|  | _||_|  ||_| _|");
+    }
+
+    #[test]
+    fn render_display() {
+        let disp: Display = EXAMPLE.parse().unwrap();
+        // Inputs decode to 8523796401, outputs to 5353.
+        let expected = concat!(
+            " _  _  _  _  _  _  _     _        _  _  _  _\n",
+            "|_||_  _| _|  ||_||_ |_|| |  | : |_  _||_  _|\n",
+            "|_| _||_  _|  | _||_|  ||_|  |    _| _| _| _|\n",
+        );
+        assert_eq!(disp.render_decoded().unwrap(), expected);
+
+        // Straight through, the 10 wires ("ab") light the top and top left.
+        let scrambled = concat!(
+            " _     _  _  _  _     _  _  _        _     _\n",
+            "|_||_| _||_||_ |_||_||  |_||   : |_||_||_||_|\n",
+            "|_|| | _|  |   | ||_|| ||_       | |  || |  |\n",
+        );
+        assert_eq!(disp.render_scrambled(), scrambled);
+    }
+
+    #[test]
+    fn seven_segment_wiring_is_unique() {
+        let data = fs::read_to_string(TEST_INPUT).unwrap();
+        let p: Problem = data.parse().unwrap();
+        let alphabet = Alphabet::seven_segment();
+        for disp in p.displays.iter() {
+            assert_eq!(alphabet.wirings(&disp.input_wires).len(), 1);
+        }
+    }
+
+    #[test]
+    fn ambiguous_wirings() {
+        // Both wirings of a mirrored pair of symbols fit.
+        let alphabet = Alphabet::new(2, vec![0b01, 0b10]);
+        let patterns = vec![Wires(0b01), Wires(0b10)];
+        let wirings = alphabet.wirings(&patterns);
+        assert_eq!(wirings, vec![vec![0, 1], vec![1, 0]]);
+
+        // Wires beyond the alphabet never fit.
+        assert!(alphabet.wirings(&[Wires(0b100)]).is_empty());
+    }
+
+    #[test]
+    fn fourteen_segment_wiring() {
+        let symbols: Vec<u32> = (1..=14).map(|n| (1 << n) - 1).collect();
+        let alphabet = Alphabet::new(14, symbols.clone());
+        let wiring = vec![3, 12, 0, 7, 1, 13, 5, 9, 2, 11, 4, 8, 10, 6];
+
+        // Scramble every symbol with the inverse of `wiring`.
+        let patterns: Vec<Wires> = symbols
+            .iter()
+            .map(|sym| {
+                let mut bits = 0;
+                for (wire, segment) in wiring.iter().enumerate() {
+                    bits |= ((sym >> segment) & 1) << wire;
+                }
+                Wires(bits)
+            })
+            .collect();
+
+        assert_eq!(alphabet.wirings(&patterns), vec![wiring]);
+    }
+
+    #[test]
+    fn thirty_two_segment_wiring() {
+        // Every bit of a `u32` is a segment, so no wire is out of range.
+        let symbols: Vec<u32> = (1..=32).map(|n| u32::MAX >> (32 - n)).collect();
+        let alphabet = Alphabet::new(32, symbols.clone());
+        let patterns: Vec<Wires> = symbols.iter().map(|&sym| Wires(sym)).collect();
+        assert_eq!(
+            alphabet.wirings(&patterns),
+            vec![(0..32).collect::<Vec<_>>()]
+        );
+    }
+
+    #[test]
+    fn solve_2() {
+        let data = fs::read_to_string(INPUT).unwrap();
+        let p: Problem = data.parse().unwrap();
+        println!("Sum of output values: {}", p.sum_output_values().unwrap())
+    }
 }
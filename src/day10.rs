@@ -1,160 +1,909 @@
-#[allow(unused_imports)]
+#![allow(dead_code)]
+use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 
 pub const INPUT: &str = "input/day10.txt";
 
-enum StackError<Parenthesis> {
-    Mismatch(Parenthesis),
-    Empty,
-    Parse,
+#[derive(Debug)]
+pub struct GrammarError {
+    details: String,
 }
 
-enum Parenthesis {
-    Round,
-    Square,
-    Curly,
-    Tri,
+impl GrammarError {
+    fn new(msg: &str) -> GrammarError {
+        GrammarError {
+            details: msg.to_string(),
+        }
+    }
 }
 
-impl fmt::Display for Parenthesis {
+impl fmt::Display for GrammarError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Round => write!(f, "{}", ")"),
-            Self::Square => write!(f, "{}", "]"),
-            Self::Curly => write!(f, "{}", "}"),
-            Self::Tri => write!(f, "{}", ">"),
+        write!(f, "ERROR: {}", self.details)
+    }
+}
+
+impl Error for GrammarError {}
+
+//------------------------------------------------------------------
+
+/// A bracket pair together with the points it scores when a line is corrupted
+/// by its closer, and when its closer is needed to complete a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pair {
+    pub open: char,
+    pub close: char,
+    pub corruption: usize,
+    pub completion: usize,
+}
+
+/// A quoted string, with an optional character escaping the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    pub quote: char,
+    pub escape: Option<char>,
+}
+
+/// The bracket language a line is checked against.
+///
+/// A grammar can be given in code or read from a config file with one pair per
+/// line and the characters to ignore on an `ignore` line. Brackets inside
+/// strings and comments are skipped if the grammar declares them, and
+/// `ignore-unknown` skips every character that is not a bracket:
+///
+/// ```text
+/// # open close corruption completion
+/// ( ) 3 1
+/// [ ] 57 2
+/// ignore \s\t
+/// string " \\
+/// line-comment //
+/// block-comment /* */
+/// ignore-unknown
+/// ```
+///
+/// `\s`, `\t` and `\\` stand for a space, a tab and a backslash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    pairs: Vec<Pair>,
+    ignored: Vec<char>,
+    ignore_unknown: bool,
+    strings: Vec<Quote>,
+    line_comments: Vec<String>,
+    block_comments: Vec<(String, String)>,
+}
+
+impl Grammar {
+    pub fn new(pairs: Vec<Pair>, ignored: Vec<char>) -> Result<Self, GrammarError> {
+        let mut seen = ignored.clone();
+        for pair in pairs.iter() {
+            for c in [pair.open, pair.close] {
+                if seen.contains(&c) {
+                    return Err(GrammarError::new(&format!("'{}' is used twice", c)));
+                }
+                seen.push(c);
+            }
+        }
+        Ok(Self {
+            pairs,
+            ignored,
+            ..Default::default()
+        })
+    }
+
+    /// Fails if `delimiter` uses a bracket or an ignored character, which
+    /// it would take over.
+    fn check_delimiter(&self, delimiter: &str) -> Result<(), GrammarError> {
+        let taken = |c: char| self.is_ignored(c) || self.opening(c).or(self.closing(c)).is_some();
+        match delimiter.chars().find(|&c| taken(c)) {
+            Some(c) => Err(GrammarError::new(&format!("'{}' is used twice", c))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn with_string(mut self, quote: char, escape: Option<char>) -> Result<Self, GrammarError> {
+        self.check_delimiter(&quote.to_string())?;
+        self.strings.push(Quote { quote, escape });
+        Ok(self)
+    }
+
+    pub fn with_line_comment(mut self, start: &str) -> Result<Self, GrammarError> {
+        if start.is_empty() {
+            return Err(GrammarError::new("A line comment needs a start."));
+        }
+        self.check_delimiter(start)?;
+        self.line_comments.push(start.to_string());
+        Ok(self)
+    }
+
+    pub fn with_block_comment(mut self, start: &str, end: &str) -> Result<Self, GrammarError> {
+        if start.is_empty() || end.is_empty() {
+            return Err(GrammarError::new(
+                "A block comment needs a start and an end.",
+            ));
         }
+        self.check_delimiter(start)?;
+        self.check_delimiter(end)?;
+        self.block_comments
+            .push((start.to_string(), end.to_string()));
+        Ok(self)
+    }
+
+    /// Skip every character that is not a bracket instead of reporting it.
+    pub fn ignoring_unknown(mut self) -> Self {
+        self.ignore_unknown = true;
+        self
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    fn opening(&self, c: char) -> Option<usize> {
+        self.pairs.iter().position(|pair| pair.open == c)
+    }
+
+    fn closing(&self, c: char) -> Option<usize> {
+        self.pairs.iter().position(|pair| pair.close == c)
+    }
+
+    fn is_ignored(&self, c: char) -> bool {
+        self.ignored.contains(&c)
     }
 }
 
-struct ParStack(Vec<Parenthesis>);
+/// The four bracket pairs of the puzzle.
+impl Default for Grammar {
+    fn default() -> Self {
+        let pair = |open, close, corruption, completion| Pair {
+            open,
+            close,
+            corruption,
+            completion,
+        };
+        Self {
+            pairs: vec![
+                pair('(', ')', 3, 1),
+                pair('[', ']', 57, 2),
+                pair('{', '}', 1197, 3),
+                pair('<', '>', 25137, 4),
+            ],
+            ignored: vec![],
+            ignore_unknown: false,
+            strings: vec![],
+            line_comments: vec![],
+            block_comments: vec![],
+        }
+    }
+}
 
-impl ParStack {
-    fn new() -> Self {
-        Self(vec![])
+fn unescape(s: &str) -> Result<Vec<char>, GrammarError> {
+    let mut chars = vec![];
+    let mut iter = s.chars();
+    while let Some(c) = iter.next() {
+        chars.push(match c {
+            '\\' => match iter.next() {
+                Some('s') => ' ',
+                Some('t') => '\t',
+                Some('\\') => '\\',
+                _ => return Err(GrammarError::new(&format!("Bad escape in '{}'.", s))),
+            },
+            c => c,
+        });
     }
+    Ok(chars)
+}
+
+/// First fields of the configuration lines that set up lexical modes.
+const LEXICAL_KEYWORDS: [&str; 4] = ["string", "line-comment", "block-comment", "ignore-unknown"];
 
-    fn check(&mut self, c: &char) -> Result<Parenthesis, StackError<char>> {
-        match c {
-            '(' => {
-                self.0.push(Parenthesis::Round);
-                Ok(Parenthesis::Round)
+impl FromStr for Grammar {
+    type Err = GrammarError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pairs = vec![];
+        let mut ignored = vec![];
+        let mut lexical = vec![];
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
             }
-            ')' => match self.0.pop() {
-                Some(Parenthesis::Round) => Ok(Parenthesis::Round),
-                Some(_) => Err(StackError::Mismatch(*c)),
-                None => Err(StackError::Empty),
-            },
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let err = |msg: &str| GrammarError::new(&format!("line {}: {}", i + 1, msg));
 
-            '[' => {
-                self.0.push(Parenthesis::Square);
-                Ok(Parenthesis::Square)
+            if fields[0] == "ignore" {
+                for field in fields[1..].iter() {
+                    ignored.extend(unescape(field)?);
+                }
+                continue;
             }
-            ']' => match self.0.pop() {
-                Some(Parenthesis::Square) => Ok(Parenthesis::Square),
-                Some(_) => Err(StackError::Mismatch(*c)),
-                None => Err(StackError::Empty),
-            },
+            if LEXICAL_KEYWORDS.contains(&fields[0]) {
+                lexical.push((i, fields));
+                continue;
+            }
+
+            let (open, close, corruption, completion) = match fields[..] {
+                [open, close, corruption, completion] => (open, close, corruption, completion),
+                _ => return Err(err("Expected 'open close corruption completion'.")),
+            };
+            let single = |field: &str| -> Result<char, GrammarError> {
+                match unescape(field)?[..] {
+                    [c] => Ok(c),
+                    _ => Err(err(&format!("'{}' is not a single character.", field))),
+                }
+            };
+            let score = |field: &str| {
+                field
+                    .parse::<usize>()
+                    .map_err(|e| err(&format!("'{}': {}", field, e)))
+            };
+            pairs.push(Pair {
+                open: single(open)?,
+                close: single(close)?,
+                corruption: score(corruption)?,
+                completion: score(completion)?,
+            });
+        }
+
+        let mut grammar = Grammar::new(pairs, ignored)?;
+        for (i, fields) in lexical {
+            let err = |msg: &str| GrammarError::new(&format!("line {}: {}", i + 1, msg));
+            let text = |field: &str| -> Result<String, GrammarError> {
+                Ok(unescape(field)?.into_iter().collect())
+            };
+            let single = |field: &str| -> Result<char, GrammarError> {
+                match unescape(field)?[..] {
+                    [c] => Ok(c),
+                    _ => Err(err(&format!("'{}' is not a single character.", field))),
+                }
+            };
+            grammar = match fields[..] {
+                ["string", quote] => grammar.with_string(single(quote)?, None)?,
+                ["string", quote, escape] => {
+                    grammar.with_string(single(quote)?, Some(single(escape)?))?
+                }
+                ["line-comment", start] => grammar.with_line_comment(&text(start)?)?,
+                ["block-comment", start, end] => {
+                    grammar.with_block_comment(&text(start)?, &text(end)?)?
+                }
+                ["ignore-unknown"] => grammar.ignoring_unknown(),
+                _ => return Err(err(&format!("Cannot read '{}'.", fields.join(" ")))),
+            };
+        }
+        Ok(grammar)
+    }
+}
+
+/// What the lexer is in the middle of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Code,
+    String { quote: Quote, escaped: bool },
+    LineComment,
+    BlockComment { end: Vec<char> },
+}
+
+/// Tells code apart from strings and comments, one character at a time.
+struct Lexer<'a> {
+    grammar: &'a Grammar,
+    mode: Mode,
+    /// Characters of a delimiter still to be skipped.
+    skip: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(grammar: &'a Grammar) -> Self {
+        Self {
+            grammar,
+            mode: Mode::Code,
+            skip: 0,
+        }
+    }
+
+    /// Whether `chars[at]` is code, to be called for every `at` in order.
+    /// Delimiters of strings and comments count as part of them.
+    fn is_code(&mut self, chars: &[char], at: usize) -> bool {
+        let starts_with = |s: &str| {
+            s.chars()
+                .eq(chars[at..].iter().copied().take(s.chars().count()))
+        };
+        if self.skip > 0 {
+            self.skip -= 1;
+            return false;
+        }
 
-            '{' => {
-                self.0.push(Parenthesis::Curly);
-                Ok(Parenthesis::Curly)
+        let c = chars[at];
+        match &mut self.mode {
+            Mode::Code => {
+                if let Some((start, end)) = self
+                    .grammar
+                    .block_comments
+                    .iter()
+                    .find(|(start, _)| starts_with(start))
+                {
+                    self.skip = start.chars().count() - 1;
+                    self.mode = Mode::BlockComment {
+                        end: end.chars().collect(),
+                    };
+                } else if self.grammar.line_comments.iter().any(|s| starts_with(s)) {
+                    self.mode = Mode::LineComment;
+                } else if let Some(quote) = self.grammar.strings.iter().find(|q| q.quote == c) {
+                    self.mode = Mode::String {
+                        quote: *quote,
+                        escaped: false,
+                    };
+                } else {
+                    return true;
+                }
             }
-            '}' => match self.0.pop() {
-                Some(Parenthesis::Curly) => Ok(Parenthesis::Curly),
-                Some(_) => Err(StackError::Mismatch(*c)),
-                None => Err(StackError::Empty),
-            },
+            Mode::String { quote, escaped } => {
+                if *escaped {
+                    *escaped = false;
+                } else if Some(c) == quote.escape {
+                    *escaped = true;
+                } else if c == quote.quote {
+                    self.mode = Mode::Code;
+                }
+            }
+            Mode::LineComment => {
+                if c == '\n' {
+                    self.mode = Mode::Code;
+                }
+            }
+            Mode::BlockComment { end } => {
+                if chars[at..].starts_with(end) {
+                    self.skip = end.len() - 1;
+                    self.mode = Mode::Code;
+                }
+            }
+        }
+        false
+    }
+}
+
+//------------------------------------------------------------------
 
-            '<' => {
-                self.0.push(Parenthesis::Tri);
-                Ok(Parenthesis::Tri)
+/// Why a line failed to check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackError {
+    /// A closer that does not match the innermost open bracket.
+    Mismatch { expected: char, found: char },
+    /// A closer with no open bracket left.
+    Empty(char),
+    /// A character the grammar does not know.
+    Parse(char),
+}
+
+impl fmt::Display for StackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Mismatch { expected, found } => {
+                write!(f, "expected `{}`, found `{}`", expected, found)
             }
-            '>' => match self.0.pop() {
-                Some(Parenthesis::Tri) => Ok(Parenthesis::Tri),
-                Some(_) => Err(StackError::Mismatch(*c)),
-                None => Err(StackError::Empty),
-            },
-            _ => return Err(StackError::Parse),
+            Self::Empty(c) => write!(f, "unmatched closing `{}`", c),
+            Self::Parse(c) => write!(f, "unknown character `{}`", c),
         }
     }
+}
 
-    fn complete(mut self) -> usize {
-        let mut pts = 0;
-        while self.0.len() > 0 {
-            pts *= 5;
+/// A completion score too large for a `usize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreOverflow;
 
-            let par = self.0.pop().unwrap();
-            pts += match par {
-                Parenthesis::Round => 1,
-                Parenthesis::Square => 2,
-                Parenthesis::Curly => 3,
-                Parenthesis::Tri => 4,
-            };
+impl fmt::Display for ScoreOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "completion score does not fit in a usize")
+    }
+}
+
+impl Error for ScoreOverflow {}
+
+/// An error in a line, located by its 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub error: StackError,
+    /// The brackets still open when the error occurred, outermost first.
+    pub open: Vec<char>,
+    pub text: String,
+}
+
+/// Renders the diagnostic compiler-style, with a caret under the offending
+/// character.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // Keep tabs so the caret lines up with the text above it.
+        let indent: String = self
+            .text
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        writeln!(f, "error: {}", self.error)?;
+        writeln!(f, "{}--> {}:{}", gutter, self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", number, self.text)?;
+        match self.error {
+            StackError::Mismatch { expected, .. } => {
+                writeln!(f, "{} | {}^ expected `{}`", gutter, indent, expected)?
+            }
+            _ => writeln!(f, "{} | {}^", gutter, indent)?,
         }
-        pts
+        let open: String = self.open.iter().collect();
+        write!(f, "{} = open brackets: {}", gutter, open)
     }
 }
 
-fn check_line(line: &str) -> usize {
-    let mut stack = ParStack::new();
-    for c in line.chars() {
-        return match stack.check(&c) {
-            Ok(_) => continue,
-            Err(StackError::Empty) => continue, // We ran out of characters
-            Err(StackError::Parse) => continue, // Unknown character
-            Err(StackError::Mismatch(c)) => match c {
-                // Corrupted line!!
-                ')' => 3,
-                ']' => 57,
-                '}' => 1197,
-                '>' => 25137,
-                _ => 0,
+/// The result of checking a single line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Valid,
+    /// Every bracket matched, but `open` is left unclosed, outermost first.
+    Incomplete {
+        open: Vec<char>,
+    },
+    Corrupted(Diagnostic),
+}
+
+/// Indices of the pairs opened so far.
+struct ParStack<'a> {
+    grammar: &'a Grammar,
+    stack: Vec<usize>,
+}
+
+impl<'a> ParStack<'a> {
+    fn new(grammar: &'a Grammar) -> Self {
+        Self {
+            grammar,
+            stack: vec![],
+        }
+    }
+
+    /// Push or pop `c`. Ignored characters leave the stack alone and return
+    /// `None`, brackets return the index of their pair.
+    fn check(&mut self, c: &char) -> Result<Option<usize>, StackError> {
+        if self.grammar.is_ignored(*c) {
+            return Ok(None);
+        }
+        if let Some(pair) = self.grammar.opening(*c) {
+            self.stack.push(pair);
+            return Ok(Some(pair));
+        }
+        match self.grammar.closing(*c) {
+            Some(pair) => match self.stack.last() {
+                Some(&open) if open == pair => Ok(self.stack.pop()),
+                Some(&open) => Err(StackError::Mismatch {
+                    expected: self.grammar.pairs[open].close,
+                    found: *c,
+                }),
+                None => Err(StackError::Empty(*c)),
             },
-        };
+            None if self.grammar.ignore_unknown => Ok(None),
+            None => Err(StackError::Parse(*c)),
+        }
+    }
+
+    fn open(&self) -> Vec<char> {
+        self.stack
+            .iter()
+            .map(|&pair| self.grammar.pairs[pair].open)
+            .collect()
+    }
+
+    /// The closers needed to finish the line, innermost first.
+    fn completion(&self) -> String {
+        self.stack
+            .iter()
+            .rev()
+            .map(|&pair| self.grammar.pairs[pair].close)
+            .collect()
+    }
+
+    fn complete(mut self) -> Result<usize, ScoreOverflow> {
+        let mut pts: usize = 0;
+        while let Some(pair) = self.stack.pop() {
+            pts = pts
+                .checked_mul(5)
+                .and_then(|pts| pts.checked_add(self.grammar.pairs[pair].completion))
+                .ok_or(ScoreOverflow)?;
+        }
+        Ok(pts)
+    }
+}
+
+/// Check line number `line` (1-based), stopping at the first error.
+pub fn diagnose(grammar: &Grammar, line: usize, text: &str) -> Outcome {
+    scan(grammar, line, text)
+}
+
+/// Check a whole source file at once, so brackets, strings and block
+/// comments may span several lines.
+pub fn check_source(grammar: &Grammar, source: &str) -> Outcome {
+    scan(grammar, 1, source)
+}
+
+fn scan(grammar: &Grammar, first_line: usize, text: &str) -> Outcome {
+    let chars: Vec<char> = text.chars().collect();
+    let mut lexer = Lexer::new(grammar);
+    let mut stack = ParStack::new(grammar);
+    let (mut line, mut column, mut line_start) = (first_line, 0, 0);
+
+    for (at, c) in chars.iter().enumerate() {
+        let is_code = lexer.is_code(&chars, at);
+        if *c == '\n' {
+            line += 1;
+            column = 0;
+            line_start = at + 1;
+            continue;
+        }
+        column += 1;
+        if !is_code || *c == '\r' {
+            continue;
+        }
+
+        if let Err(error) = stack.check(c) {
+            return Outcome::Corrupted(Diagnostic {
+                line,
+                column,
+                error,
+                open: stack.open(),
+                text: chars[line_start..]
+                    .iter()
+                    .take_while(|&&c| c != '\n' && c != '\r')
+                    .collect(),
+            });
+        }
+    }
+    match stack.stack.is_empty() {
+        true => Outcome::Valid,
+        false => Outcome::Incomplete { open: stack.open() },
+    }
+}
+
+/// Check every line of `data`.
+pub fn check(grammar: &Grammar, data: &str) -> Vec<Outcome> {
+    data.lines()
+        .enumerate()
+        .map(|(i, text)| diagnose(grammar, i + 1, text))
+        .collect()
+}
+
+/// Render the diagnostics of all corrupted lines, separated by blank lines.
+pub fn report(grammar: &Grammar, data: &str) -> String {
+    let diagnostics: Vec<String> = check(grammar, data)
+        .into_iter()
+        .filter_map(|outcome| match outcome {
+            Outcome::Corrupted(diagnostic) => Some(diagnostic.to_string()),
+            _ => None,
+        })
+        .collect();
+    diagnostics.join("\n\n")
+}
+
+/// A single-character fix, at a 1-based column of the original line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Replace {
+        column: usize,
+        found: char,
+        with: char,
+    },
+    Delete {
+        column: usize,
+        found: char,
+    },
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Replace {
+                column,
+                found,
+                with,
+            } => write!(f, "{}: replace `{}` with `{}`", column, found, with),
+            Self::Delete { column, found } => write!(f, "{}: delete `{}`", column, found),
+        }
     }
-    return 0;
 }
 
-fn complete_line(line: &str) -> Option<usize> {
-    let mut stack = ParStack::new();
-    for c in line.chars() {
-        return match stack.check(&c) {
-            Ok(_) => continue,                    // Next char
-            Err(StackError::Empty) => None,       // We ran out of characters
-            Err(StackError::Parse) => continue,   // Unknown character
-            Err(StackError::Mismatch(_)) => None, // Corrupt line
+/// The edits that make a line completable, and the closers that complete it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+    pub edits: Vec<Edit>,
+    pub completion: String,
+    /// The repaired and completed line.
+    pub text: String,
+}
+
+/// Run the checker over `chars`, returning the position and kind of the
+/// first error, or the stack if there is none.
+fn first_error<'a>(
+    grammar: &'a Grammar,
+    chars: &[(usize, char)],
+) -> Result<ParStack<'a>, (usize, StackError)> {
+    let plain: Vec<char> = chars.iter().map(|(_, c)| *c).collect();
+    let mut lexer = Lexer::new(grammar);
+    let mut stack = ParStack::new(grammar);
+    for (at, c) in plain.iter().enumerate() {
+        if lexer.is_code(&plain, at) {
+            stack.check(c).map_err(|error| (at, error))?;
+        }
+    }
+    Ok(stack)
+}
+
+/// Original column the check gets to before failing, if it fails at all.
+fn reach(grammar: &Grammar, chars: &[(usize, char)]) -> usize {
+    match first_error(grammar, chars) {
+        Ok(_) => usize::MAX,
+        Err((at, _)) => chars[at].0,
+    }
+}
+
+/// Fix every error of the line with a single replacement or deletion and
+/// complete the result. At a mismatched closer the expected closer is
+/// substituted, unless deleting the closer lets the check get further.
+/// Unmatched closers and unknown characters are deleted.
+pub fn repair(grammar: &Grammar, text: &str) -> Repair {
+    // Characters tagged with their original column.
+    let mut chars: Vec<(usize, char)> = text.chars().enumerate().map(|(i, c)| (i + 1, c)).collect();
+    let mut edits = vec![];
+
+    let stack = loop {
+        let (at, error) = match first_error(grammar, &chars) {
+            Ok(stack) => break stack,
+            Err(err) => err,
+        };
+        let (column, found) = chars[at];
+
+        let mut deleted = chars.clone();
+        deleted.remove(at);
+        let (next, edit) = match error {
+            StackError::Mismatch { expected, .. } => {
+                let mut replaced = chars.clone();
+                replaced[at].1 = expected;
+                match reach(grammar, &deleted) > reach(grammar, &replaced) {
+                    true => (deleted, Edit::Delete { column, found }),
+                    false => (
+                        replaced,
+                        Edit::Replace {
+                            column,
+                            found,
+                            with: expected,
+                        },
+                    ),
+                }
+            }
+            StackError::Empty(_) | StackError::Parse(_) => {
+                (deleted, Edit::Delete { column, found })
+            }
         };
+        chars = next;
+        edits.push(edit);
+    };
+
+    let completion = stack.completion();
+    let mut text: String = chars.iter().map(|(_, c)| c).collect();
+    text.push_str(&completion);
+    Repair {
+        edits,
+        completion,
+        text,
     }
-    return Some(stack.complete());
 }
 
-pub fn solve_1(data: &String) -> usize {
+/// The closers completing an incomplete line, `None` for corrupted lines.
+pub fn completion(grammar: &Grammar, text: &str) -> Option<String> {
+    match diagnose(grammar, 1, text) {
+        Outcome::Corrupted(_) => None,
+        _ => Some(repair(grammar, text).completion),
+    }
+}
+
+/// Write every line of `data` repaired and completed.
+pub fn write_repaired<W: Write>(grammar: &Grammar, data: &str, out: &mut W) -> io::Result<()> {
+    for line in data.lines() {
+        writeln!(out, "{}", repair(grammar, line).text)?;
+    }
+    Ok(())
+}
+
+fn check_line(grammar: &Grammar, line: &str) -> usize {
+    match diagnose(grammar, 1, line) {
+        Outcome::Corrupted(Diagnostic {
+            error: StackError::Mismatch { found, .. },
+            ..
+        }) => grammar.pairs[grammar.closing(found).unwrap()].corruption,
+        _ => 0,
+    }
+}
+
+fn complete_line(grammar: &Grammar, line: &str) -> Option<Result<usize, ScoreOverflow>> {
+    match diagnose(grammar, 1, line) {
+        Outcome::Incomplete { open } => {
+            let mut stack = ParStack::new(grammar);
+            stack.stack = open.iter().filter_map(|&c| grammar.opening(c)).collect();
+            Some(stack.complete())
+        }
+        _ => None,
+    }
+}
+
+/// Total corruption score of all lines under `grammar`.
+pub fn corruption_score(grammar: &Grammar, data: &str) -> usize {
     let mut pts = 0;
     for line in data.lines() {
-        pts += check_line(line);
+        pts += check_line(grammar, line);
     }
     pts
 }
 
-pub fn solve_2(data: &String) -> usize {
+/// Median completion score of the incomplete lines under `grammar`. Fails
+/// if the median itself is too large for a `usize`.
+pub fn completion_score(grammar: &Grammar, data: &str) -> Result<usize, ScoreOverflow> {
     let mut pts = vec![];
+    let mut overflowed = 0;
     for line in data.lines() {
-        match complete_line(line) {
-            Some(res) => pts.push(res),
+        match complete_line(grammar, line) {
+            Some(Ok(res)) => pts.push(res),
+            Some(Err(ScoreOverflow)) => overflowed += 1,
             None => continue,
         };
     }
-    pts.sort();
-    pts[pts.len() / 2]
+    // Scores that overflowed are larger than all others, so they sort last.
+    let mid = (pts.len() + overflowed) / 2;
+    if mid >= pts.len() && overflowed > 0 {
+        return Err(ScoreOverflow);
+    }
+    pts.sort_unstable();
+    Ok(pts[mid])
+}
+
+/// Scores gathered from any number of lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scores {
+    pub corruption: usize,
+    /// Completion scores of the incomplete lines, in no particular order.
+    pub completions: Vec<usize>,
+    /// Incomplete lines left out of `completions` as their score does not
+    /// fit in a `usize`.
+    pub overflowed: usize,
 }
 
+impl Scores {
+    fn add_line(&mut self, grammar: &Grammar, line: &str) {
+        self.corruption += check_line(grammar, line);
+        match complete_line(grammar, line) {
+            Some(Ok(pts)) => self.completions.push(pts),
+            Some(Err(ScoreOverflow)) => self.overflowed += 1,
+            None => (),
+        }
+    }
+
+    fn merge(&mut self, other: Scores) {
+        self.corruption += other.corruption;
+        self.completions.extend(other.completions);
+        self.overflowed += other.overflowed;
+    }
+
+    /// Middle completion score, `None` if no line was incomplete. Fails if
+    /// the middle score is one of those too large for a `usize`.
+    pub fn median_completion(&mut self) -> Result<Option<usize>, ScoreOverflow> {
+        // Scores that overflowed are larger than all others, so they sort last.
+        let mid = (self.completions.len() + self.overflowed) / 2;
+        if mid < self.completions.len() {
+            Ok(Some(*self.completions.select_nth_unstable(mid).1))
+        } else if self.overflowed > 0 {
+            Err(ScoreOverflow)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Lines handed to a worker at once.
+const BATCH_SIZE: usize = 4096;
+
+fn worker_error(msg: &str) -> io::Error {
+    io::Error::other(msg)
+}
+
+/// Send the lines of `reader` in batches until it runs out or fails, or
+/// every worker has stopped.
+fn send_batches<R: BufRead>(reader: R, sender: mpsc::SyncSender<Vec<String>>) -> io::Result<()> {
+    let stopped = |_| worker_error("Worker threads stopped early.");
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for line in reader.lines() {
+        batch.push(line?);
+        if batch.len() == BATCH_SIZE {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+            sender.send(full).map_err(stopped)?;
+        }
+    }
+    if !batch.is_empty() {
+        sender.send(batch).map_err(stopped)?;
+    }
+    Ok(())
+}
+
+/// Check every line of `reader` on `threads` worker threads. Lines are read
+/// in batches, and at most two batches per worker wait in memory at a time,
+/// so arbitrarily large inputs can be streamed. Fails rather than blocks if
+/// the workers panic.
+pub fn check_reader<R: BufRead>(
+    grammar: &Grammar,
+    reader: R,
+    threads: usize,
+) -> io::Result<Scores> {
+    let threads = threads.max(1);
+    let (sender, receiver) = mpsc::sync_channel::<Vec<String>>(2 * threads);
+    // Each worker holds the receiver, so it is dropped, and sending fails,
+    // once every worker has stopped.
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                scope.spawn(move || {
+                    let mut scores = Scores::default();
+                    loop {
+                        // Release the lock before working on the batch.
+                        let batch = receiver.lock().unwrap().recv();
+                        let batch = match batch {
+                            Ok(batch) => batch,
+                            Err(_) => return scores,
+                        };
+                        for line in batch.iter() {
+                            scores.add_line(grammar, line);
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(receiver);
+
+        let read = send_batches(reader, sender);
+
+        let mut scores = Scores::default();
+        let mut panicked = false;
+        for worker in workers {
+            match worker.join() {
+                Ok(worker_scores) => scores.merge(worker_scores),
+                Err(_) => panicked = true,
+            }
+        }
+        if panicked {
+            return Err(worker_error("Worker thread panicked."));
+        }
+        read.map(|_| scores)
+    })
+}
+
+pub fn solve_1(data: &str) -> usize {
+    corruption_score(&Grammar::default(), data)
+}
+
+pub fn solve_2(data: &str) -> usize {
+    completion_score(&Grammar::default(), data).unwrap()
+}
+
+#[cfg(test)]
 mod tests {
-    #[allow(unused)]
     pub const INPUT_TEST: &str = "input/day10_test.txt";
+    pub const GRAMMAR_TEST: &str = "input/day10_grammar.txt";
 
-    #[allow(unused_imports)]
-    use super::{solve_1, solve_2};
+    use super::*;
 
     #[test]
     fn test_1() {
@@ -173,4 +922,304 @@ mod tests {
         println!("{}", res);
         assert_eq!(288957, res)
     }
+
+    #[test]
+    fn test_grammar_from_file() {
+        let grammar = Grammar::from_file(GRAMMAR_TEST).unwrap();
+        assert_eq!(grammar, Grammar::default());
+
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        assert_eq!(corruption_score(&grammar, &data), 26397);
+        assert_eq!(completion_score(&grammar, &data), Ok(288957));
+    }
+
+    #[test]
+    fn test_custom_grammar() {
+        let grammar: Grammar = "
+            # Pascal-ish blocks
+            ( ) 1 1
+            B E 10 2
+            ignore \\s ab
+        "
+        .parse()
+        .unwrap();
+
+        assert_eq!(grammar.ignored, vec![' ', 'a', 'b']);
+        assert_eq!(check_line(&grammar, "B (a) b E"), 0);
+        assert_eq!(check_line(&grammar, "B ( E )"), 10);
+        assert_eq!(
+            complete_line(&grammar, "B ( (a b"),
+            Some(Ok((5 + 1) * 5 + 2))
+        );
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let grammar = Grammar::default();
+        let outcomes = check(&grammar, "()\n[<>({\n{([(<{}[<>[]}>{[]{[(<()>\n)\n(x)");
+        assert_eq!(outcomes[0], Outcome::Valid);
+        assert_eq!(
+            outcomes[1],
+            Outcome::Incomplete {
+                open: vec!['[', '(', '{']
+            }
+        );
+        assert_eq!(
+            outcomes[2],
+            Outcome::Corrupted(Diagnostic {
+                line: 3,
+                column: 13,
+                error: StackError::Mismatch {
+                    expected: ']',
+                    found: '}'
+                },
+                open: vec!['{', '(', '[', '(', '<', '['],
+                text: "{([(<{}[<>[]}>{[]{[(<()>".to_string(),
+            })
+        );
+        assert!(matches!(
+            &outcomes[3],
+            Outcome::Corrupted(Diagnostic {
+                column: 1,
+                error: StackError::Empty(')'),
+                ..
+            })
+        ));
+        assert!(matches!(
+            &outcomes[4],
+            Outcome::Corrupted(Diagnostic {
+                column: 2,
+                error: StackError::Parse('x'),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_report() {
+        let grammar = Grammar::default();
+        let data = "[]\n[<>({}\n{([(<{}[<>[]}>{[]{[(<()>";
+        let expected = "\
+error: expected `]`, found `}`
+ --> 3:13
+  |
+3 | {([(<{}[<>[]}>{[]{[(<()>
+  |             ^ expected `]`
+  = open brackets: {([(<[";
+        assert_eq!(report(&grammar, data), expected);
+
+        // Line 3 of the example is the line above.
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        let report = report(&grammar, &data);
+        assert!(report.starts_with(expected));
+        assert_eq!(report.matches("error: ").count(), 5);
+        // Carets sit under columns 13, 9, 8, 11 and 17, after the `  | ` gutter.
+        let carets: Vec<usize> = report.lines().filter_map(|line| line.find('^')).collect();
+        assert_eq!(carets, [16, 12, 11, 14, 20]);
+    }
+
+    #[test]
+    fn test_completion() {
+        let grammar = Grammar::default();
+        let line = "[({(<(())[]>[[{[]{<()<>>";
+        assert_eq!(completion(&grammar, line).unwrap(), "}}]])})]");
+        assert_eq!(completion(&grammar, "()").unwrap(), "");
+        assert_eq!(completion(&grammar, "(]"), None);
+    }
+
+    #[test]
+    fn test_repair() {
+        let grammar = Grammar::default();
+
+        let repaired = repair(&grammar, "{([(<{}[<>[]}>{[]{[(<()>");
+        assert_eq!(
+            repaired.edits[0],
+            Edit::Replace {
+                column: 13,
+                found: '}',
+                with: ']'
+            }
+        );
+        assert_eq!(diagnose(&grammar, 1, &repaired.text), Outcome::Valid);
+
+        // Deleting the stray `)` keeps the rest of the line intact.
+        let repaired = repair(&grammar, "[()){}]");
+        assert_eq!(
+            repaired.edits,
+            vec![Edit::Delete {
+                column: 4,
+                found: ')'
+            }]
+        );
+        assert_eq!(repaired.text, "[(){}]");
+
+        let repaired = repair(&grammar, ")(x");
+        assert_eq!(repaired.edits.len(), 2);
+        assert_eq!(repaired.text, "()");
+    }
+
+    #[test]
+    fn test_write_repaired() {
+        let grammar = Grammar::default();
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        let mut out = vec![];
+        write_repaired(&grammar, &data, &mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), data.lines().count());
+        for line in out.lines() {
+            assert_eq!(diagnose(&grammar, 1, line), Outcome::Valid);
+        }
+    }
+
+    #[test]
+    fn test_check_reader() {
+        let grammar = Grammar::default();
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+
+        for threads in [1, 4] {
+            let mut scores = check_reader(&grammar, data.as_bytes(), threads).unwrap();
+            assert_eq!(scores.corruption, 26397);
+            assert_eq!(scores.median_completion(), Ok(Some(288957)));
+        }
+
+        // Many batches, split across workers.
+        let big = format!("{}\n", data.trim_end()).repeat(1001);
+        let mut scores = check_reader(&grammar, big.as_bytes(), 3).unwrap();
+        assert_eq!(scores.corruption, 1001 * 26397);
+        assert_eq!(scores.completions.len(), 1001 * 5);
+        assert_eq!(scores.median_completion(), Ok(Some(288957)));
+
+        let mut scores = check_reader(&grammar, "".as_bytes(), 2).unwrap();
+        assert_eq!(scores.median_completion(), Ok(None));
+    }
+
+    #[test]
+    fn test_score_overflow() {
+        let grammar = Grammar::default();
+        // 28 open brackets still fit, 29 do not.
+        let fits = "(".repeat(28);
+        let deep = "(".repeat(29);
+        assert!(complete_line(&grammar, &fits).unwrap().is_ok());
+        assert_eq!(complete_line(&grammar, &deep), Some(Err(ScoreOverflow)));
+
+        let data = format!("{}\n{}\n{}\n", fits, deep, deep);
+        assert_eq!(completion_score(&grammar, &data), Err(ScoreOverflow));
+        let data = format!("{}\n{}\n{}\n", fits, fits, deep);
+        assert!(completion_score(&grammar, &data).is_ok());
+
+        // Enough lines to fill the channel many times over.
+        let big = format!("{}\n", "(".repeat(40)).repeat(40 * BATCH_SIZE);
+        let mut scores = check_reader(&grammar, big.as_bytes(), 4).unwrap();
+        assert_eq!(scores.overflowed, 40 * BATCH_SIZE);
+        assert_eq!(scores.median_completion(), Err(ScoreOverflow));
+    }
+
+    #[test]
+    fn test_workers_stopped() {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        drop(receiver);
+        let lines = "(\n".repeat(2 * BATCH_SIZE);
+        assert!(send_batches(lines.as_bytes(), sender).is_err());
+    }
+
+    const SOURCE: &str = r#"fn main() {
+    // A smiley :)
+    let s = "(\")[";
+    let c = ']';
+    /* unbalanced { in
+       a block comment */
+    println!("{}", s);
+}
+"#;
+
+    fn rust_grammar() -> Grammar {
+        Grammar::default()
+            .with_string('"', Some('\\'))
+            .unwrap()
+            .with_string('\'', None)
+            .unwrap()
+            .with_line_comment("//")
+            .unwrap()
+            .with_block_comment("/*", "*/")
+            .unwrap()
+            .ignoring_unknown()
+    }
+
+    #[test]
+    fn test_lexical_modes() {
+        assert_eq!(check_source(&rust_grammar(), SOURCE), Outcome::Valid);
+
+        // Without lexical modes the smiley's `)` runs into `main`'s brace.
+        let grammar = Grammar::default().ignoring_unknown();
+        let outcome = check_source(&grammar, SOURCE);
+        assert!(matches!(
+            outcome,
+            Outcome::Corrupted(Diagnostic {
+                line: 2,
+                column: 18,
+                ..
+            })
+        ));
+
+        let source = SOURCE.replace("\"{}\", s)", "\"{}\", s]");
+        match check_source(&rust_grammar(), &source) {
+            Outcome::Corrupted(diagnostic) => {
+                assert_eq!((diagnostic.line, diagnostic.column), (7, 21));
+                assert_eq!(diagnostic.text, "    println!(\"{}\", s];");
+                assert_eq!(diagnostic.open, vec!['{', '(']);
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+
+        // Lines are checked on their own as well.
+        let grammar = rust_grammar();
+        assert_eq!(diagnose(&grammar, 1, "f(\")\") // )"), Outcome::Valid);
+        assert_eq!(completion(&grammar, "f(\")\", /* ) */ ['}'").unwrap(), "])");
+    }
+
+    #[test]
+    fn test_lexical_grammar_from_str() {
+        let grammar: Grammar = r#"
+            ( ) 3 1
+            [ ] 57 2
+            { } 1197 3
+            < > 25137 4
+            string " \\
+            string '
+            line-comment //
+            block-comment /* */
+            ignore-unknown
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(grammar, rust_grammar());
+        assert!("( ) 3 1\nstring ab".parse::<Grammar>().is_err());
+        assert!("( ) 3 1\nnonsense".parse::<Grammar>().is_err());
+
+        // Only the keywords start lexical lines, pairs may use any letters.
+        let grammar: Grammar = "b e 10 2\nstring q".parse().unwrap();
+        assert_eq!(grammar.pairs[0].open, 'b');
+        assert_eq!(grammar.strings.len(), 1);
+        assert_eq!(check_source(&grammar, "bbqeqee"), Outcome::Valid);
+
+        assert!(Grammar::default().with_line_comment("").is_err());
+        assert!(Grammar::default().with_block_comment("", "*/").is_err());
+        assert!(Grammar::default().with_block_comment("/*", "").is_err());
+
+        // Delimiters may not take over brackets or ignored characters.
+        assert!("( ) 3 1\nstring (".parse::<Grammar>().is_err());
+        assert!("( ) 3 1\nignore \"\nstring \"".parse::<Grammar>().is_err());
+        assert!(Grammar::default().with_line_comment("<!").is_err());
+        assert!(Grammar::default().with_block_comment("/*", "*>").is_err());
+    }
+
+    #[test]
+    fn test_grammar_errors() {
+        assert!("( ) 3".parse::<Grammar>().is_err());
+        assert!("( ) x 1".parse::<Grammar>().is_err());
+        assert!("() ) 3 1".parse::<Grammar>().is_err());
+        assert!("( ) 3 1\n[ ( 57 2".parse::<Grammar>().is_err());
+        assert!("ignore \\q".parse::<Grammar>().is_err());
+    }
 }
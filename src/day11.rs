@@ -1,7 +1,14 @@
 #![allow(dead_code)]
 
 use self::Direction::*;
-use std::{error::Error, fmt::Display, str::FromStr};
+use crate::export::{Frame, GifWriter, Ramp};
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt::Display,
+    io::{self, Write},
+    str::FromStr,
+};
 
 macro_rules! skip_none {
     ($res:expr) => {
@@ -30,18 +37,135 @@ impl Direction {
             .iter()
             .copied()
     }
+
+    /// Row and column offset of the neighbour in this direction.
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (-1, 1),
+            Direction::DownLeft => (1, -1),
+            Direction::DownRight => (1, 1),
+        }
+    }
+}
+
+/// Which cells a flash charges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Neighborhood {
+    /// The four orthogonal neighbours.
+    VonNeumann,
+    /// All eight surrounding cells.
+    Moore,
+    /// The six neighbours of a hexagonal grid stored in rows, with odd rows
+    /// shifted half a cell to the right.
+    Hexagonal,
+    /// Arbitrary row and column offsets.
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighborhood {
+    /// Offsets for cells in even and in odd rows.
+    fn offsets(&self) -> [Vec<(isize, isize)>; 2] {
+        match self {
+            Self::VonNeumann => {
+                let offsets: Vec<_> = [Up, Right, Down, Left]
+                    .iter()
+                    .map(Direction::offset)
+                    .collect();
+                [offsets.clone(), offsets]
+            }
+            Self::Moore => {
+                let offsets: Vec<_> = Direction::iterator().map(|d| d.offset()).collect();
+                [offsets.clone(), offsets]
+            }
+            Self::Hexagonal => [
+                vec![(-1, -1), (-1, 0), (0, 1), (1, 0), (1, -1), (0, -1)],
+                vec![(-1, 0), (-1, 1), (0, 1), (1, 1), (1, 0), (0, -1)],
+            ],
+            Self::Custom(offsets) => [offsets.clone(), offsets.clone()],
+        }
+    }
+}
+
+/// What happens to neighbours beyond the edge of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Boundary {
+    /// They do not exist.
+    Clipped,
+    /// The grid wraps around, so they are found on the opposite edge.
+    Toroidal,
+    /// They are mirrored back into the grid about the edge cells, so a cell on
+    /// the edge may charge the same neighbour twice.
+    Reflective,
+}
+
+impl Boundary {
+    /// Map index `k` onto `0..len`, if the boundary allows it.
+    fn apply(&self, k: isize, len: usize) -> Option<usize> {
+        let len = len as isize;
+        let k = match self {
+            Boundary::Clipped => k,
+            Boundary::Toroidal => k.rem_euclid(len.max(1)),
+            Boundary::Reflective if k < 0 => -k,
+            Boundary::Reflective if k >= len => 2 * (len - 1) - k,
+            Boundary::Reflective => k,
+        };
+        match 0 <= k && k < len {
+            true => Some(k as usize),
+            false => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Grid<const SIZE: usize> {
-    data: [[u8; SIZE]; SIZE],
-    flashed: [[bool; SIZE]; SIZE],
+struct Grid {
+    data: Vec<Vec<u8>>,
+    flashed: Vec<Vec<bool>>,
+    neighborhood: Neighborhood,
+    boundary: Boundary,
+    /// `neighborhood.offsets()`, cached.
+    offsets: [Vec<(isize, isize)>; 2],
 }
 
-impl<const SIZE: usize> Grid<SIZE> {
-    fn new(data: [[u8; SIZE]; SIZE]) -> Self {
-        let flashed = [[false; SIZE]; SIZE];
-        Self { data, flashed }
+impl Grid {
+    fn new(data: Vec<Vec<u8>>) -> Self {
+        let width = data.first().map_or(0, |row| row.len());
+        assert!(
+            data.iter().all(|row| row.len() == width),
+            "Grid rows differ in length."
+        );
+        let flashed = vec![vec![false; width]; data.len()];
+        let neighborhood = Neighborhood::Moore;
+        Self {
+            data,
+            flashed,
+            offsets: neighborhood.offsets(),
+            neighborhood,
+            boundary: Boundary::Clipped,
+        }
+    }
+
+    fn with_neighborhood(mut self, neighborhood: Neighborhood) -> Self {
+        self.offsets = neighborhood.offsets();
+        self.neighborhood = neighborhood;
+        self
+    }
+
+    fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    fn height(&self) -> usize {
+        self.data.len()
+    }
+
+    fn width(&self) -> usize {
+        self.data.first().map_or(0, |row| row.len())
     }
 
     fn increase(&mut self) {
@@ -52,49 +176,58 @@ impl<const SIZE: usize> Grid<SIZE> {
         }
     }
 
-    fn get_neighbor(&mut self, i: usize, j: usize, direction: Direction) -> Option<(usize, usize)> {
-        let i = i as isize;
-        let j = j as isize;
-        let (i, j) = match direction {
-            Direction::Up => (i - 1, j),
-            Direction::Down => (i + 1, j),
-            Direction::Left => (i, j - 1),
-            Direction::Right => (i, j + 1),
-            Direction::UpLeft => (i - 1, j - 1),
-            Direction::UpRight => (i - 1, j + 1),
-            Direction::DownLeft => (i + 1, j - 1),
-            Direction::DownRight => (i + 1, j + 1),
-        };
-        if i < 0 || j < 0 || i >= SIZE as isize || j >= SIZE as isize {
-            return None;
-        }
-        Some((i as usize, j as usize))
+    fn get_neighbor(&self, i: usize, j: usize, offset: (isize, isize)) -> Option<(usize, usize)> {
+        let i = self.boundary.apply(i as isize + offset.0, self.height())?;
+        let j = self.boundary.apply(j as isize + offset.1, self.width())?;
+        Some((i, j))
     }
 
-    fn flash_one(&mut self, i: usize, j: usize) {
-        let flashed = &mut self.flashed[i][j];
-        if self.data[i][j] > 9 && !*flashed {
-            *flashed = true;
-            for direction in Direction::iterator() {
-                let (neighbor_i, neighbor_j) = skip_none!(self.get_neighbor(i, j, direction));
-                self.data[neighbor_i][neighbor_j] += 1;
-                self.flash_one(neighbor_i, neighbor_j);
+    /// Flash every charged cell, and every neighbour they charge in turn,
+    /// returning the flashes in the order they happened. Cells charged by
+    /// the increase flash first, at depth 0; a cell taken past 9 by a flash
+    /// at depth `d` flashes at depth `d + 1`. Uses a queue rather than
+    /// recursion, as cascades on large grids can reach far deeper than the
+    /// stack.
+    fn flash(&mut self) -> Vec<Flash> {
+        let mut queue = VecDeque::new();
+        for i in 0..self.height() {
+            for j in 0..self.width() {
+                if self.data[i][j] > 9 {
+                    queue.push_back((i, j, 0));
+                }
             }
         }
-    }
 
-    fn flash(&mut self) {
-        for i in 0..SIZE {
-            for j in 0..SIZE {
-                self.flash_one(i, j);
+        let mut flashes = vec![];
+        while let Some((i, j, depth)) = queue.pop_front() {
+            if self.data[i][j] <= 9 || self.flashed[i][j] {
+                continue;
+            }
+            self.flashed[i][j] = true;
+            flashes.push(Flash {
+                row: i,
+                col: j,
+                order: flashes.len(),
+                depth,
+            });
+            for k in 0..self.offsets[i % 2].len() {
+                let offset = self.offsets[i % 2][k];
+                let (neighbor_i, neighbor_j) = skip_none!(self.get_neighbor(i, j, offset));
+                self.data[neighbor_i][neighbor_j] += 1;
+                // Every cell crosses the threshold once, and flashes one wave
+                // after the charge that took it across.
+                if self.data[neighbor_i][neighbor_j] == 10 {
+                    queue.push_back((neighbor_i, neighbor_j, depth + 1));
+                }
             }
         }
+        flashes
     }
 
     fn reset(&mut self) -> usize {
         let mut count = 0;
-        for i in 0..SIZE {
-            for j in 0..SIZE {
+        for i in 0..self.height() {
+            for j in 0..self.width() {
                 let cell = &mut self.data[i][j];
                 if *cell > 9 {
                     count += 1;
@@ -113,6 +246,14 @@ impl<const SIZE: usize> Grid<SIZE> {
         self.reset()
     }
 
+    /// Step and tell `observer` which cells flashed.
+    fn step_observed<O: StepObserver>(&mut self, observer: &mut O) -> usize {
+        self.increase();
+        let flashes = self.flash();
+        observer.on_step(&flashes);
+        self.reset()
+    }
+
     fn all_equal(&self) -> bool {
         for row in self.data.iter() {
             for cell in row.iter() {
@@ -123,27 +264,159 @@ impl<const SIZE: usize> Grid<SIZE> {
         }
         true
     }
+
+    /// Energy levels as an image frame.
+    fn frame(&self) -> Frame {
+        Frame::from_fn(self.width(), self.height(), |i, j| self.data[i][j])
+    }
+
+    /// Record the grid and its next `steps` steps as an animated GIF, each
+    /// cell `scale` pixels wide and coloured by its energy.
+    fn write_gif<W: Write>(&mut self, out: W, steps: usize, scale: usize) -> io::Result<W> {
+        let mut gif = GifWriter::new(out, self.width(), self.height(), scale, Ramp::heat(10))?;
+        gif.add_frame(&self.frame(), 10)?;
+        for _ in 0..steps {
+            self.step();
+            gif.add_frame(&self.frame(), 10)?;
+        }
+        gif.finish()
+    }
+
+    /// Step a copy of the grid until it revisits a state. As every cell is
+    /// reset to at most 9, there are finitely many states and this always
+    /// terminates.
+    fn analyze(&self) -> CycleReport {
+        let mut grid = self.clone();
+        let mut seen = HashMap::new();
+        let mut synchronized = None;
+        let mut step = 0;
+        loop {
+            if let Some(&first) = seen.get(&grid.data) {
+                return CycleReport {
+                    pre_period: first,
+                    period: step - first,
+                    synchronized,
+                };
+            }
+            seen.insert(grid.data.clone(), step);
+
+            grid.step();
+            step += 1;
+            if synchronized.is_none() && grid.all_equal() {
+                synchronized = Some(step);
+            }
+        }
+    }
+
+    /// The first step after which all cells are equal.
+    fn first_synchronized(&self) -> Result<usize, Box<dyn Error>> {
+        let report = self.analyze();
+        report.synchronized.ok_or_else(|| {
+            format!(
+                "Never synchronizes: enters a cycle of period {} after {} steps.",
+                report.period, report.pre_period
+            )
+            .into()
+        })
+    }
+}
+
+/// A cell flashing during a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Flash {
+    row: usize,
+    col: usize,
+    /// Position in the step's cascade, starting at 0.
+    order: usize,
+    /// Wave of the cascade the flash belongs to, see `Grid::flash`.
+    depth: usize,
+}
+
+/// Receives the flashes of every step, in cascade order.
+trait StepObserver {
+    fn on_step(&mut self, flashes: &[Flash]);
+}
+
+impl<F: FnMut(&[Flash])> StepObserver for F {
+    fn on_step(&mut self, flashes: &[Flash]) {
+        self(flashes)
+    }
+}
+
+/// Statistics gathered over any number of steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FlashStats {
+    steps: usize,
+    /// Most flashes in a single step.
+    largest_cascade: usize,
+    /// Most waves in a single step.
+    deepest_cascade: usize,
+    /// How often each cell flashed.
+    frequency: Vec<Vec<usize>>,
+}
+
+impl FlashStats {
+    fn new(grid: &Grid) -> Self {
+        Self {
+            steps: 0,
+            largest_cascade: 0,
+            deepest_cascade: 0,
+            frequency: vec![vec![0; grid.width()]; grid.height()],
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.frequency.iter().flatten().sum()
+    }
+}
+
+impl StepObserver for FlashStats {
+    fn on_step(&mut self, flashes: &[Flash]) {
+        self.steps += 1;
+        self.largest_cascade = self.largest_cascade.max(flashes.len());
+        if let Some(last) = flashes.last() {
+            self.deepest_cascade = self.deepest_cascade.max(last.depth + 1);
+        }
+        for flash in flashes {
+            self.frequency[flash.row][flash.col] += 1;
+        }
+    }
+}
+
+/// The long-term behaviour of a grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CycleReport {
+    /// Steps before the grid enters its cycle.
+    pre_period: usize,
+    /// Length of the cycle.
+    period: usize,
+    /// First step after which all cells are equal, if there is one.
+    synchronized: Option<usize>,
 }
 
-impl<const SIZE: usize> FromStr for Grid<SIZE> {
+impl FromStr for Grid {
     type Err = Box<dyn Error>;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut data = [[0; SIZE]; SIZE];
-        for (i, line) in s.lines().enumerate() {
-            for (j, c) in line.chars().enumerate() {
-                let entry = data
-                    .get_mut(i)
-                    .ok_or("Row out of bounds".to_string())?
-                    .get_mut(j)
-                    .ok_or("Coumn out of bounds".to_string())?;
-                *entry = c.to_digit(10).ok_or("Non-digit character.".to_string())? as u8;
+        let mut data = vec![];
+        for line in s.lines() {
+            let mut row = Vec::with_capacity(line.len());
+            for c in line.chars() {
+                row.push(c.to_digit(10).ok_or("Non-digit character.".to_string())? as u8);
             }
+            if row.len()
+                != data
+                    .first()
+                    .map_or(row.len(), |first: &Vec<u8>| first.len())
+            {
+                return Err("Rows differ in length.".into());
+            }
+            data.push(row);
         }
         Ok(Self::new(data))
     }
 }
 
-impl<const SIZE: usize> Display for Grid<SIZE> {
+impl Display for Grid {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         for row in self.data.iter() {
             for cell in row.iter() {
@@ -158,7 +431,7 @@ impl<const SIZE: usize> Display for Grid<SIZE> {
 pub fn part_1() -> usize {
     const INPUT_FILE: &str = "input/day11.txt";
     let data = std::fs::read_to_string(INPUT_FILE).expect("Failed to read input file.");
-    let mut grid: Grid<10> = Grid::from_str(&data).expect("Failed to parse input file.");
+    let mut grid = Grid::from_str(&data).expect("Failed to parse input file.");
     let mut count = 0;
     for _ in 0..100 {
         count += grid.step();
@@ -169,15 +442,9 @@ pub fn part_1() -> usize {
 pub fn part_2() -> usize {
     const INPUT_FILE: &str = "input/day11.txt";
     let data = std::fs::read_to_string(INPUT_FILE).expect("Failed to read input file.");
-    let mut grid: Grid<10> = Grid::from_str(&data).expect("Failed to parse input file.");
-    let mut i = 0;
-    loop {
-        grid.step();
-        i += 1;
-        if grid.all_equal() {
-            return i;
-        }
-    }
+    let grid = Grid::from_str(&data).expect("Failed to parse input file.");
+    grid.first_synchronized()
+        .expect("Octopuses never synchronize.")
 }
 
 #[cfg(test)]
@@ -187,47 +454,106 @@ mod tests {
     use super::*;
 
     const TEST_INPUT_FILE: &str = "input/day11_test.txt";
-    const TEST_GRID: Grid<5> = Grid {
-        data: [
-            [1, 1, 1, 1, 1],
-            [1, 9, 9, 9, 1],
-            [1, 9, 1, 9, 1],
-            [1, 9, 9, 9, 1],
-            [1, 1, 1, 1, 1],
-        ],
-        flashed: [[false; 5]; 5],
-    };
+    const TEST_GRID: [[u8; 5]; 5] = [
+        [1, 1, 1, 1, 1],
+        [1, 9, 9, 9, 1],
+        [1, 9, 1, 9, 1],
+        [1, 9, 9, 9, 1],
+        [1, 1, 1, 1, 1],
+    ];
+
+    fn test_grid() -> Grid {
+        Grid::new(TEST_GRID.iter().map(|row| row.to_vec()).collect())
+    }
 
     #[test]
     fn test_from_str() {
         let data = "11111\n19991\n19191\n19991\n11111";
-        let grid: Grid<5> = Grid::from_str(data).unwrap();
-        assert_eq!(grid.data, TEST_GRID.data);
+        let grid = Grid::from_str(data).unwrap();
+        assert_eq!(grid.data, TEST_GRID);
+    }
+
+    #[test]
+    fn test_from_str_rectangular() {
+        let data = "111\n191\n191\n111";
+        let grid = Grid::from_str(data).unwrap();
+        assert_eq!((grid.height(), grid.width()), (4, 3));
+
+        assert!(Grid::from_str("111\n19\n111").is_err());
+        assert!(Grid::from_str("1a1").is_err());
     }
 
     #[test]
     fn test_flash() {
-        let mut grid = TEST_GRID.clone();
+        let mut grid = test_grid();
         grid.increase();
-        grid.flash();
+        let flashes = grid.flash();
         grid.reset();
-        let expected = Grid {
-            data: [
-                [3, 4, 5, 4, 3],
-                [4, 0, 0, 0, 4],
-                [5, 0, 0, 0, 5],
-                [4, 0, 0, 0, 4],
-                [3, 4, 5, 4, 3],
-            ],
-            flashed: [[false; 5]; 5],
+
+        // The ring of nines flashes first and charges the middle to 10.
+        assert_eq!(flashes.len(), 9);
+        assert!(flashes[..8].iter().all(|flash| flash.depth == 0));
+        let middle = Flash {
+            row: 2,
+            col: 2,
+            order: 8,
+            depth: 1,
         };
-        assert_eq!(grid.data, expected.data);
+        assert_eq!(flashes[8], middle);
+
+        let expected = [
+            [3, 4, 5, 4, 3],
+            [4, 0, 0, 0, 4],
+            [5, 0, 0, 0, 5],
+            [4, 0, 0, 0, 4],
+            [3, 4, 5, 4, 3],
+        ];
+        assert_eq!(grid.data, expected);
+    }
+
+    #[test]
+    fn test_flash_chain() {
+        // The chain runs with and against the scan order alike.
+        for (data, depths) in [("98", [0, 1]), ("89", [1, 0])].iter() {
+            let mut grid = Grid::from_str(data).unwrap();
+            let mut stats = FlashStats::new(&grid);
+            grid.step_observed(&mut stats);
+            assert_eq!(stats.deepest_cascade, 2);
+
+            let mut grid = Grid::from_str(data).unwrap();
+            grid.increase();
+            let flashes = grid.flash();
+            assert_eq!(flashes.len(), 2);
+            for flash in flashes.iter() {
+                assert_eq!(flash.depth, depths[flash.col]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flash_depth_of_crossing() {
+        // (1, 0) is charged by both depth 0 and depth 1 flashes, but only
+        // the latter takes it past 9.
+        let mut grid = Grid::new(vec![vec![9, 8], vec![7, 0]]);
+        grid.increase();
+        let flashes = grid.flash();
+        let depths: Vec<_> = flashes.iter().map(|f| (f.row, f.col, f.depth)).collect();
+        assert_eq!(depths, [(0, 0, 0), (0, 1, 1), (1, 0, 2)]);
+    }
+
+    #[test]
+    fn test_flash_rectangular() {
+        let mut grid = Grid::from_str("1111\n1991\n1111").unwrap();
+        let count = grid.step();
+        let expected = [[3, 4, 4, 3], [3, 0, 0, 3], [3, 4, 4, 3]];
+        assert_eq!(count, 2);
+        assert_eq!(grid.data, expected);
     }
 
     #[test]
     fn test_large_grid() {
         let data = fs::read_to_string(TEST_INPUT_FILE).unwrap();
-        let mut grid: Grid<10> = Grid::from_str(&data).unwrap();
+        let mut grid = Grid::from_str(&data).unwrap();
 
         let f_out = fs::File::create("output/day11_test.txt").unwrap();
         let mut f_out = std::io::BufWriter::new(f_out);
@@ -241,4 +567,163 @@ mod tests {
         }
         assert_eq!(count, 1656);
     }
+
+    #[test]
+    fn test_write_gif() {
+        let data = fs::read_to_string(TEST_INPUT_FILE).unwrap();
+        let mut grid = Grid::from_str(&data).unwrap();
+
+        let gif = grid.write_gif(vec![], 100, 4).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+        // 40x40 pixels.
+        assert_eq!(&gif[6..10], &[40, 0, 40, 0]);
+        assert_eq!(gif.last(), Some(&0x3B));
+    }
+
+    #[test]
+    fn test_cascade_stress() {
+        // Every cell flashes in a single cascade, which would take one stack
+        // frame per cell if propagated recursively.
+        const SIZE: usize = 1000;
+        let mut grid = Grid::new(vec![vec![9; SIZE]; SIZE]);
+        assert_eq!(grid.step(), SIZE * SIZE);
+        assert!(grid.all_equal());
+        assert_eq!(grid.data[0][0], 0);
+    }
+
+    /// Step with only cell `(i, j)` about to flash, so the grid shows which
+    /// cells its neighbourhood reaches.
+    fn single_flash(grid: Grid, i: usize, j: usize) -> Vec<Vec<u8>> {
+        let mut grid = grid;
+        grid.data[i][j] = 9;
+        grid.step();
+        grid.data
+    }
+
+    fn threes() -> Grid {
+        Grid::new(vec![vec![3; 4]; 3])
+    }
+
+    #[test]
+    fn test_von_neumann() {
+        let grid = threes().with_neighborhood(Neighborhood::VonNeumann);
+        let expected = [[4, 5, 4, 4], [5, 0, 5, 4], [4, 5, 4, 4]];
+        assert_eq!(single_flash(grid, 1, 1), expected);
+    }
+
+    #[test]
+    fn test_hexagonal() {
+        let grid = threes().with_neighborhood(Neighborhood::Hexagonal);
+        // Row 1 is odd, so it is shifted right: its upper and lower
+        // neighbours are in columns 1 and 2.
+        let expected = [[4, 5, 5, 4], [5, 0, 5, 4], [4, 5, 5, 4]];
+        assert_eq!(single_flash(grid, 1, 1), expected);
+
+        let grid = threes().with_neighborhood(Neighborhood::Hexagonal);
+        let expected = [[5, 0, 5, 4], [5, 5, 4, 4], [4, 4, 4, 4]];
+        assert_eq!(single_flash(grid, 0, 1), expected);
+    }
+
+    #[test]
+    fn test_custom_neighborhood() {
+        // A knight's moves.
+        let knight = vec![
+            (-2, -1),
+            (-2, 1),
+            (-1, -2),
+            (-1, 2),
+            (1, -2),
+            (1, 2),
+            (2, -1),
+            (2, 1),
+        ];
+        let grid = threes().with_neighborhood(Neighborhood::Custom(knight));
+        let expected = [[4, 4, 4, 5], [4, 0, 4, 4], [4, 4, 4, 5]];
+        assert_eq!(single_flash(grid, 1, 1), expected);
+    }
+
+    #[test]
+    fn test_toroidal() {
+        let grid = threes().with_boundary(Boundary::Toroidal);
+        let expected = [[0, 5, 4, 5], [5, 5, 4, 5], [5, 5, 4, 5]];
+        assert_eq!(single_flash(grid, 0, 0), expected);
+    }
+
+    #[test]
+    fn test_reflective() {
+        let grid = threes().with_boundary(Boundary::Reflective);
+        // Up and left are mirrored onto down and right.
+        let expected = [[0, 6, 4, 4], [6, 8, 4, 4], [4, 4, 4, 4]];
+        assert_eq!(single_flash(grid, 0, 0), expected);
+    }
+
+    #[test]
+    fn test_boundary_apply() {
+        assert_eq!(Boundary::Clipped.apply(-1, 5), None);
+        assert_eq!(Boundary::Clipped.apply(5, 5), None);
+        assert_eq!(Boundary::Toroidal.apply(-1, 5), Some(4));
+        assert_eq!(Boundary::Toroidal.apply(6, 5), Some(1));
+        assert_eq!(Boundary::Reflective.apply(-1, 5), Some(1));
+        assert_eq!(Boundary::Reflective.apply(5, 5), Some(3));
+        assert_eq!(Boundary::Reflective.apply(-1, 1), None);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let data = fs::read_to_string(TEST_INPUT_FILE).unwrap();
+        let grid = Grid::from_str(&data).unwrap();
+        let report = grid.analyze();
+        assert_eq!(report.synchronized, Some(195));
+        // Once synchronized, all cells flash together every tenth step.
+        assert_eq!(report.period, 10);
+        assert_eq!(report.pre_period, 195);
+        assert_eq!(grid.first_synchronized().unwrap(), 195);
+    }
+
+    #[test]
+    fn test_never_synchronizes() {
+        // Without neighbours the cells count on independently, out of phase.
+        let grid = Grid::from_str("01").unwrap();
+        let grid = grid.with_neighborhood(Neighborhood::Custom(vec![]));
+        let report = grid.analyze();
+        assert_eq!(
+            report,
+            CycleReport {
+                pre_period: 0,
+                period: 10,
+                synchronized: None
+            }
+        );
+        assert!(grid.first_synchronized().is_err());
+    }
+
+    #[test]
+    fn test_observer() {
+        let data = fs::read_to_string(TEST_INPUT_FILE).unwrap();
+        let mut grid = Grid::from_str(&data).unwrap();
+        let mut stats = FlashStats::new(&grid);
+
+        let mut count = 0;
+        for _ in 0..100 {
+            count += grid.step_observed(&mut stats);
+        }
+        assert_eq!(count, 1656);
+        assert_eq!(stats.total(), 1656);
+        assert_eq!(stats.steps, 100);
+
+        for _ in 100..195 {
+            grid.step_observed(&mut stats);
+        }
+        assert_eq!(stats.largest_cascade, 100);
+
+        // Closures observe as well. Synchronized at step 195, the cells
+        // flash together again ten steps later.
+        let mut orders = vec![];
+        for _ in 195..205 {
+            grid.step_observed(&mut |flashes: &[Flash]| {
+                orders.extend(flashes.iter().map(|flash| flash.order))
+            });
+        }
+        assert_eq!(orders, (0..100).collect::<Vec<_>>());
+    }
 }
@@ -1,10 +1,14 @@
-use std::collections::HashSet;
+#![allow(dead_code)]
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
-use std::isize;
+use std::io::{self, Write};
 use std::num::ParseIntError;
 use std::str;
 
+use crate::export::{self, Frame};
+use crate::ocr::{self, OcrError};
+
 pub const INPUT: &str = "input/day13.txt";
 
 #[derive(Debug)]
@@ -38,6 +42,12 @@ impl From<ParseIntError> for DotError {
     }
 }
 
+impl From<OcrError> for DotError {
+    fn from(err: OcrError) -> Self {
+        DotError::new(&err.to_string())
+    }
+}
+
 impl From<&str> for DotError {
     fn from(err: &str) -> Self {
         DotError::new(err)
@@ -54,112 +64,767 @@ pub struct Dot {
 
 impl Dot {
     pub fn fold_x(mut self, x: isize) -> Self {
-        match self.x < x {
-            true => return self,
-            false => {
-                self.x = 2 * x - self.x;
-                return self;
-            }
+        if self.x > x {
+            self.x = 2 * x - self.x;
         }
+        self
     }
 
     pub fn fold_y(mut self, y: isize) -> Self {
-        match self.y < y {
-            true => return self,
-            false => {
-                self.y = 2 * y - self.y;
-                return self;
-            }
+        if self.y > y {
+            self.y = 2 * y - self.y;
+        }
+        self
+    }
+}
+
+impl str::FromStr for Dot {
+    type Err = DotError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (x_str, y_str) = s.split_once(",").ok_or("No comma found.")?;
+
+        let x = x_str.parse::<isize>()?;
+        let y = y_str.parse::<isize>()?;
+
+        Ok(Self { x, y })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fold {
+    X(isize),
+    Y(isize),
+}
+
+impl Fold {
+    pub fn apply(&self, dot: Dot) -> Dot {
+        match *self {
+            Fold::X(x) => dot.fold_x(x),
+            Fold::Y(y) => dot.fold_y(y),
+        }
+    }
+
+    pub fn is_on_line(&self, dot: &Dot) -> bool {
+        match *self {
+            Fold::X(x) => dot.x == x,
+            Fold::Y(y) => dot.y == y,
+        }
+    }
+
+    /// Whether `dot` would be mirrored to a negative coordinate.
+    pub fn is_beyond(&self, dot: &Dot) -> bool {
+        match *self {
+            Fold::X(x) => dot.x > 2 * x,
+            Fold::Y(y) => dot.y > 2 * y,
         }
     }
+}
 
-    pub fn fold_from_str(mut self, s: &str) -> Result<Self, DotError> {
-        let s = &s[11..];
+impl str::FromStr for Fold {
+    type Err = DotError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s
+            .strip_prefix("fold along ")
+            .ok_or("Expected 'fold along'.")?;
         let (dir, val_str) = s.split_once("=").ok_or("Could not split on '='.")?;
 
         let val = val_str.parse::<isize>()?;
 
         match dir {
-            "x" => Ok(self.fold_x(val)),
-            "y" => Ok(self.fold_y(val)),
-            _ => Err(DotError::new("Could not fold")),
+            "x" => Ok(Fold::X(val)),
+            "y" => Ok(Fold::Y(val)),
+            _ => Err(DotError::new(&format!("Unknown fold axis '{}'.", dir))),
         }
     }
 }
 
-impl str::FromStr for Dot {
+impl fmt::Display for Fold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fold::X(x) => write!(f, "fold along x={}", x),
+            Fold::Y(y) => write!(f, "fold along y={}", y),
+        }
+    }
+}
+
+//------------------------------------------------------------------
+
+/// A transparent sheet of dots together with the fold instructions that
+/// have not been applied to it yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paper {
+    dots: HashSet<Dot>,
+    folds: Vec<Fold>,
+}
+
+impl Paper {
+    pub fn new(dots: HashSet<Dot>, folds: Vec<Fold>) -> Self {
+        Self { dots, folds }
+    }
+
+    pub fn dots(&self) -> &HashSet<Dot> {
+        &self.dots
+    }
+
+    pub fn folds(&self) -> &[Fold] {
+        &self.folds
+    }
+
+    pub fn len(&self) -> usize {
+        self.dots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dots.is_empty()
+    }
+
+    /// Applies `fold` to every dot. Pending instructions are kept.
+    pub fn fold(&self, fold: &Fold) -> Self {
+        Self {
+            dots: self.dots.iter().map(|d| fold.apply(d.clone())).collect(),
+            folds: self.folds.clone(),
+        }
+    }
+
+    /// Applies the next pending instruction, if there is one.
+    pub fn fold_next(&self) -> Option<Self> {
+        let (fold, rest) = self.folds.split_first()?;
+        let mut paper = self.fold(fold);
+        paper.folds = rest.to_vec();
+        Some(paper)
+    }
+
+    /// Applies up to `max_folds` pending instructions, or all of them.
+    pub fn fold_all(&self, max_folds: Option<usize>) -> Self {
+        let n = max_folds.unwrap_or(usize::MAX).min(self.folds.len());
+        let dots = self.dots.iter().map(|d| {
+            self.folds[..n]
+                .iter()
+                .fold(d.clone(), |d, fold| fold.apply(d))
+        });
+        Self {
+            dots: dots.collect(),
+            folds: self.folds[n..].to_vec(),
+        }
+    }
+
+    /// The pending instructions composed into one transform, tabulated over
+    /// the bounding box of the dots.
+    pub fn transform(&self) -> Transform {
+        let (min, max) = bounds(&self.dots).unwrap_or((Dot { x: 0, y: 0 }, Dot { x: -1, y: -1 }));
+        Transform::new(&self.folds, &min, &max)
+    }
+
+    pub fn render(&self, glyphs: Glyphs) -> String {
+        render(&self.dots, glyphs)
+    }
+
+    pub fn sheet(&self) -> Sheet {
+        Sheet::new(self.dots.clone())
+    }
+
+    /// Reads the capital letters drawn by the dots.
+    pub fn read(&self) -> Result<String, OcrError> {
+        ocr::read_points(self.dots.iter().map(|d| (d.x, d.y)))
+    }
+
+    /// Applies the pending instructions and lists every dot lying on a fold
+    /// line or folded past the edge of the paper.
+    pub fn validate(&self) -> Vec<FoldIssue> {
+        let mut issues = Vec::new();
+        let mut paper = self.clone();
+        let mut step = 0;
+        while let Some(fold) = paper.folds.first().copied() {
+            let mut on_line: Vec<Dot> = paper
+                .dots
+                .iter()
+                .filter(|d| fold.is_on_line(d))
+                .cloned()
+                .collect();
+            if !on_line.is_empty() {
+                on_line.sort_by_key(|d| (d.x, d.y));
+                issues.push(FoldIssue::OnFoldLine {
+                    step,
+                    fold,
+                    dots: on_line,
+                });
+            }
+            let mut beyond: Vec<Dot> = paper
+                .dots
+                .iter()
+                .filter(|d| fold.is_beyond(d))
+                .cloned()
+                .collect();
+            if !beyond.is_empty() {
+                beyond.sort_by_key(|d| (d.x, d.y));
+                issues.push(FoldIssue::BeyondEdge {
+                    step,
+                    fold,
+                    dots: beyond,
+                });
+            }
+            paper = paper.fold_next().unwrap();
+            step += 1;
+        }
+        issues
+    }
+}
+
+impl str::FromStr for Paper {
     type Err = DotError;
+    /// Parses the dot section, a blank line and the fold section. Errors
+    /// name the offending line.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (x_str, y_str) = s.split_once(",").ok_or("No comma found.")?;
+        let line_error =
+            |i: usize, err: DotError| DotError::new(&format!("line {}: {}", i + 1, err.details));
 
-        let x = x_str.parse::<isize>()?;
-        let y = y_str.parse::<isize>()?;
+        let mut lines = s.lines().enumerate();
+        let mut dots = HashSet::new();
+        for (i, line) in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+            dots.insert(line.trim().parse::<Dot>().map_err(|e| line_error(i, e))?);
+        }
 
-        Ok(Self { x: x, y: y })
+        let mut folds = Vec::new();
+        for (i, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            folds.push(line.trim().parse::<Fold>().map_err(|e| line_error(i, e))?);
+        }
+
+        Ok(Self { dots, folds })
     }
 }
 
-pub fn show(dots: &HashSet<Dot>) {
-    let w = dots.iter().map(|dot| dot.x).max().unwrap_or_default() + 1;
-    let h = dots.iter().map(|dot| dot.y).max().unwrap_or_default() + 1;
-    for y in 0..h {
-        for x in 0..w {
-            let d = Dot { x: x, y: y };
-            if dots.contains(&d) {
-                print!("#");
-            } else {
-                print!(".");
+/// Problems found by `Paper::validate`. `step` counts the folds before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FoldIssue {
+    /// The puzzle never puts dots on a fold line.
+    OnFoldLine {
+        step: usize,
+        fold: Fold,
+        dots: Vec<Dot>,
+    },
+    /// The fold is closer to the top or left edge than to the far edge, so
+    /// these dots end up at negative coordinates.
+    BeyondEdge {
+        step: usize,
+        fold: Fold,
+        dots: Vec<Dot>,
+    },
+}
+
+impl fmt::Display for FoldIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (step, fold, dots, what) = match self {
+            FoldIssue::OnFoldLine { step, fold, dots } => (step, fold, dots, "on the fold line"),
+            FoldIssue::BeyondEdge { step, fold, dots } => {
+                (step, fold, dots, "folded past the edge")
             }
+        };
+        write!(
+            f,
+            "fold {} ({}): {} dot(s) {}",
+            step + 1,
+            fold,
+            dots.len(),
+            what
+        )
+    }
+}
+
+//------------------------------------------------------------------
+
+/// What happened during one fold of a `History`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub fold: Fold,
+    /// The dots before the fold.
+    pub before: HashSet<Dot>,
+    /// Each dot that two or more dots were folded onto, with those dots.
+    pub merged: Vec<(Dot, Vec<Dot>)>,
+}
+
+/// A paper that remembers its folds so they can be undone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    paper: Paper,
+    steps: Vec<Step>,
+}
+
+impl History {
+    pub fn new(paper: Paper) -> Self {
+        Self {
+            paper,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn paper(&self) -> &Paper {
+        &self.paper
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Applies the next pending instruction and returns what it did.
+    pub fn fold_next(&mut self) -> Option<&Step> {
+        let fold = *self.paper.folds.first()?;
+        let mut landed: HashMap<Dot, Vec<Dot>> = HashMap::new();
+        for d in self.paper.dots.iter() {
+            landed
+                .entry(fold.apply(d.clone()))
+                .or_default()
+                .push(d.clone());
         }
-        print!("\n")
+        let mut merged: Vec<(Dot, Vec<Dot>)> = landed
+            .into_iter()
+            .filter(|(_, from)| from.len() > 1)
+            .map(|(to, mut from)| {
+                from.sort_by_key(|d| (d.x, d.y));
+                (to, from)
+            })
+            .collect();
+        merged.sort_by_key(|(d, _)| (d.x, d.y));
+
+        let next = self.paper.fold_next()?;
+        let before = std::mem::replace(&mut self.paper, next).dots;
+        self.steps.push(Step {
+            fold,
+            before,
+            merged,
+        });
+        self.steps.last()
+    }
+
+    /// Unfolds the last fold, which becomes the next pending instruction.
+    pub fn undo(&mut self) -> Option<Fold> {
+        let step = self.steps.pop()?;
+        self.paper.dots = step.before;
+        self.paper.folds.insert(0, step.fold);
+        Some(step.fold)
     }
 }
 
-//--------------------------------------------------------------
+//------------------------------------------------------------------
+
+/// The composition of all folds along one axis, tabulated over `start..end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AxisMap {
+    start: isize,
+    forward: Vec<isize>,
+    inverse: HashMap<isize, Vec<isize>>,
+}
 
-pub fn solve_1(data: &String, max_folds: Option<usize>) -> usize {
-    #[derive(PartialEq, Eq)]
-    enum Parsing {
-        Dots,
-        Folds,
+impl AxisMap {
+    fn new(lines: &[isize], start: isize, end: isize) -> Self {
+        let forward: Vec<isize> = (start..end)
+            .map(|v| {
+                lines
+                    .iter()
+                    .fold(v, |v, &l| if v > l { 2 * l - v } else { v })
+            })
+            .collect();
+        let mut inverse: HashMap<isize, Vec<isize>> = HashMap::new();
+        for (v, &w) in (start..end).zip(forward.iter()) {
+            inverse.entry(w).or_default().push(v);
+        }
+        Self {
+            start,
+            forward,
+            inverse,
+        }
     }
 
-    let mut reading_state = Parsing::Dots;
-    let mut dots = HashSet::new();
+    fn get(&self, v: isize) -> Option<isize> {
+        let i = v - self.start;
+        if i < 0 {
+            return None;
+        }
+        self.forward.get(i as usize).copied()
+    }
 
-    let mut i = 0;
-    for line in data.lines() {
-        if reading_state == Parsing::Dots {
-            let d = line.parse::<Dot>();
-            match d {
-                Ok(d) => {
-                    dots.insert(d);
-                }
-                Err(d) => {
-                    reading_state = Parsing::Folds;
-                    continue;
+    fn preimage(&self, w: isize) -> &[isize] {
+        self.inverse.get(&w).map_or(&[], |v| v.as_slice())
+    }
+}
+
+/// A sequence of folds composed into one map from original to final
+/// coordinates. Lookups inside the tabulated extent take constant time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transform {
+    folds: Vec<Fold>,
+    x: AxisMap,
+    y: AxisMap,
+}
+
+impl Transform {
+    /// Tabulates `folds` for all dots between the corners `min` and `max`.
+    pub fn new(folds: &[Fold], min: &Dot, max: &Dot) -> Self {
+        let xs: Vec<isize> = folds
+            .iter()
+            .filter_map(|f| match f {
+                Fold::X(x) => Some(*x),
+                _ => None,
+            })
+            .collect();
+        let ys: Vec<isize> = folds
+            .iter()
+            .filter_map(|f| match f {
+                Fold::Y(y) => Some(*y),
+                _ => None,
+            })
+            .collect();
+        Self {
+            folds: folds.to_vec(),
+            x: AxisMap::new(&xs, min.x, max.x + 1),
+            y: AxisMap::new(&ys, min.y, max.y + 1),
+        }
+    }
+
+    /// Where `dot` ends up. Dots outside the tabulated extent are folded one
+    /// instruction at a time.
+    pub fn apply(&self, dot: &Dot) -> Dot {
+        match (self.x.get(dot.x), self.y.get(dot.y)) {
+            (Some(x), Some(y)) => Dot { x, y },
+            _ => self.folds.iter().fold(dot.clone(), |d, f| f.apply(d)),
+        }
+    }
+
+    /// All cells of the tabulated extent that end up on `dot`.
+    pub fn preimage(&self, dot: &Dot) -> Vec<Dot> {
+        let ys = self.y.preimage(dot.y);
+        self.x
+            .preimage(dot.x)
+            .iter()
+            .flat_map(|&x| ys.iter().map(move |&y| Dot { x, y }))
+            .collect()
+    }
+}
+
+//------------------------------------------------------------------
+
+/// Dots stored as one bit per cell of a `width` x `height` sheet, each row
+/// padded to whole words. Bit `x % 64` of word `x / 64` is column `x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    width: usize,
+    height: usize,
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize) -> Self {
+        let stride = width.div_ceil(64).max(1);
+        Self {
+            width,
+            height,
+            stride,
+            words: vec![0; stride * height],
+        }
+    }
+
+    /// `None` if a dot has a negative coordinate.
+    pub fn from_dots(dots: &HashSet<Dot>) -> Option<Self> {
+        let mut bitmap = match bounds(dots) {
+            Some((min, _)) if min.x < 0 || min.y < 0 => return None,
+            Some((_, max)) => Self::new(max.x as usize + 1, max.y as usize + 1),
+            None => Self::new(0, 0),
+        };
+        for d in dots {
+            bitmap.set(d.x as usize, d.y as usize);
+        }
+        Some(bitmap)
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.words[y * self.stride + x / 64] |= 1 << (x % 64);
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.stride..(y + 1) * self.stride]
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn dots(&self) -> HashSet<Dot> {
+        let mut dots = HashSet::new();
+        for y in 0..self.height {
+            for (k, &word) in self.row(y).iter().enumerate() {
+                let mut word = word;
+                while word != 0 {
+                    let x = 64 * k + word.trailing_zeros() as usize;
+                    dots.insert(Dot {
+                        x: x as isize,
+                        y: y as isize,
+                    });
+                    word &= word - 1;
                 }
             }
+        }
+        dots
+    }
+
+    /// `None` if the fold would move dots to negative coordinates.
+    pub fn fold(&self, fold: &Fold) -> Option<Self> {
+        match *fold {
+            Fold::X(x) => self.fold_x(x),
+            Fold::Y(y) => self.fold_y(y),
+        }
+    }
+
+    fn fold_y(&self, line: isize) -> Option<Self> {
+        let last = (0..self.height)
+            .rev()
+            .find(|&y| self.row(y).iter().any(|&w| w != 0));
+        if line < 0 || last.is_some_and(|y| y as isize > 2 * line) {
+            return None;
+        }
+        let line = line as usize;
+        let height = self.height.min(line + 1);
+        let mut words = self.words[..height * self.stride].to_vec();
+        for y in line + 1..=last.unwrap_or(0) {
+            let target = 2 * line - y;
+            for (w, &v) in words[target * self.stride..(target + 1) * self.stride]
+                .iter_mut()
+                .zip(self.row(y))
+            {
+                *w |= v;
+            }
+        }
+        Some(Self {
+            width: self.width,
+            height,
+            stride: self.stride,
+            words,
+        })
+    }
+
+    fn fold_x(&self, line: isize) -> Option<Self> {
+        if line < 0 {
+            return None;
+        }
+        let inside = keep_mask(self.stride, 2 * line as usize + 1);
+        let beyond = |w: &[u64]| w.iter().zip(inside.iter()).any(|(w, m)| w & !m != 0);
+        if (0..self.height).any(|y| beyond(self.row(y))) {
+            return None;
+        }
+        let line = line as usize;
+        let mut folded = Self::new(self.width.min(line + 1), self.height);
+        // Reversing a whole row sends column x to 64 * stride - 1 - x, from
+        // where a shift by `offset` lands it on 2 * line - x.
+        let offset = 2 * line as isize - (64 * self.stride as isize - 1);
+        for y in 0..self.height {
+            let row = self.row(y);
+            let reversed: Vec<u64> = row.iter().rev().map(|w| w.reverse_bits()).collect();
+            let mirrored = shift(&reversed, offset);
+            let keep = keep_mask(row.len(), line + 1);
+            let out = &mut folded.words[y * folded.stride..(y + 1) * folded.stride];
+            for (k, w) in out.iter_mut().enumerate() {
+                // Columns past the line were mirrored, the line itself stays.
+                *w = (row[k] & keep[k]) | (mirrored.get(k).copied().unwrap_or(0) & keep[k]);
+            }
+        }
+        Some(folded)
+    }
+}
+
+/// Moves every bit of `words` up by `by` positions, or down if negative.
+fn shift(words: &[u64], by: isize) -> Vec<u64> {
+    let n = words.len();
+    let get = |i: isize| -> u64 {
+        if i < 0 || i >= n as isize {
+            0
         } else {
-            if i >= max_folds.unwrap_or(usize::MAX) {
-                break;
+            words[i as usize]
+        }
+    };
+    let (whole, bits) = (by.div_euclid(64), by.rem_euclid(64) as u32);
+    (0..n as isize)
+        .map(|k| {
+            let lo = get(k - whole);
+            let below = get(k - whole - 1);
+            match bits {
+                0 => lo,
+                _ => (lo << bits) | (below >> (64 - bits)),
             }
+        })
+        .collect()
+}
 
-            dots = dots
-                .into_iter()
-                .map(|d: Dot| d.fold_from_str(line).unwrap())
-                .collect();
-            i += 1;
+/// Words with the lowest `bits` bits set.
+fn keep_mask(len: usize, bits: usize) -> Vec<u64> {
+    (0..len)
+        .map(|k| match bits.saturating_sub(64 * k) {
+            0 => 0,
+            b if b >= 64 => u64::MAX,
+            b => (1 << b) - 1,
+        })
+        .collect()
+}
+
+/// Bitmap cells per dot below which a `Sheet` uses a bitmap. A hashed dot
+/// costs about as much memory as this many bits.
+pub const DENSE_CELLS_PER_DOT: usize = 128;
+
+/// Dots in whichever representation suits their density.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sheet {
+    Sparse(HashSet<Dot>),
+    Dense(Bitmap),
+}
+
+impl Sheet {
+    pub fn new(dots: HashSet<Dot>) -> Self {
+        match bounds(&dots) {
+            Some((min, max)) if min.x >= 0 && min.y >= 0 => {
+                // Too large an area to count is far too large for a bitmap.
+                let area = (max.x as usize)
+                    .checked_add(1)
+                    .zip((max.y as usize).checked_add(1))
+                    .and_then(|(w, h)| w.checked_mul(h));
+                let budget = DENSE_CELLS_PER_DOT.saturating_mul(dots.len());
+                match area {
+                    Some(area) if area <= budget => Sheet::Dense(Bitmap::from_dots(&dots).unwrap()),
+                    _ => Sheet::Sparse(dots),
+                }
+            }
+            _ => Sheet::Sparse(dots),
+        }
+    }
+
+    pub fn is_dense(&self) -> bool {
+        matches!(self, Sheet::Dense(_))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Sheet::Sparse(dots) => dots.len(),
+            Sheet::Dense(bitmap) => bitmap.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn dots(&self) -> HashSet<Dot> {
+        match self {
+            Sheet::Sparse(dots) => dots.clone(),
+            Sheet::Dense(bitmap) => bitmap.dots(),
+        }
+    }
+
+    /// Folds a bitmap into a smaller bitmap where it can, everything else via
+    /// the dots.
+    pub fn fold(self, fold: &Fold) -> Self {
+        match self {
+            Sheet::Dense(bitmap) => match bitmap.fold(fold) {
+                Some(bitmap) => Sheet::Dense(bitmap),
+                None => Sheet::new(bitmap.dots().into_iter().map(|d| fold.apply(d)).collect()),
+            },
+            Sheet::Sparse(dots) => Sheet::new(dots.into_iter().map(|d| fold.apply(d)).collect()),
+        }
+    }
+}
+
+//------------------------------------------------------------------
+
+/// Characters drawn for cells with and without a dot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyphs {
+    pub on: char,
+    pub off: char,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self { on: '#', off: '.' }
+    }
+}
+
+/// Top left and bottom right corner of the smallest rectangle holding all dots.
+pub fn bounds(dots: &HashSet<Dot>) -> Option<(Dot, Dot)> {
+    let x_min = dots.iter().map(|d| d.x).min()?;
+    let x_max = dots.iter().map(|d| d.x).max()?;
+    let y_min = dots.iter().map(|d| d.y).min()?;
+    let y_max = dots.iter().map(|d| d.y).max()?;
+    Some((Dot { x: x_min, y: y_min }, Dot { x: x_max, y: y_max }))
+}
+
+/// Draws the bounding box of the dots, one line per row.
+pub fn render(dots: &HashSet<Dot>, glyphs: Glyphs) -> String {
+    let mut s = String::new();
+    if let Some((min, max)) = bounds(dots) {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                match dots.contains(&Dot { x, y }) {
+                    true => s.push(glyphs.on),
+                    false => s.push(glyphs.off),
+                }
+            }
+            s.push('\n');
         }
     }
-    show(&dots);
-    dots.len()
+    s
+}
+
+/// The bounding box of the dots with level 1 for a dot and 0 otherwise.
+pub fn frame(dots: &HashSet<Dot>) -> Frame {
+    match bounds(dots) {
+        Some((min, max)) => Frame::from_fn(
+            (max.x - min.x + 1) as usize,
+            (max.y - min.y + 1) as usize,
+            |i, j| {
+                let d = Dot {
+                    x: min.x + j as isize,
+                    y: min.y + i as isize,
+                };
+                dots.contains(&d) as u8
+            },
+        ),
+        None => Frame::from_fn(0, 0, |_, _| 0),
+    }
 }
 
-pub fn solve_2(data: &String) {
-    solve_1(data, None);
+pub fn write_pbm<W: Write>(dots: &HashSet<Dot>, out: &mut W, scale: usize) -> io::Result<()> {
+    export::write_pbm(out, &frame(dots), scale)
 }
 
+pub fn show(dots: &HashSet<Dot>, glyphs: Glyphs) -> io::Result<()> {
+    io::stdout()
+        .lock()
+        .write_all(render(dots, glyphs).as_bytes())
+}
+
+//--------------------------------------------------------------
+
+pub fn solve_1(data: &str, max_folds: Option<usize>) -> Result<usize, DotError> {
+    let paper = data.parse::<Paper>()?;
+    let n = max_folds.unwrap_or(usize::MAX).min(paper.folds().len());
+    let sheet = paper.folds()[..n]
+        .iter()
+        .fold(Sheet::new(paper.dots().clone()), |sheet, f| sheet.fold(f));
+    Ok(sheet.len())
+}
+
+pub fn solve_2(data: &str) -> Result<String, DotError> {
+    let paper = data.parse::<Paper>()?.fold_all(None);
+    Ok(paper.read()?)
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
     #[allow(unused)]
@@ -170,15 +835,19 @@ mod tests {
     #[test]
     fn test_1() {
         let data = fs::read_to_string(INPUT_TEST).unwrap();
-        let result = solve_1(&data, Some(1));
+        let result = solve_1(&data, Some(1)).unwrap();
 
         assert_eq!(result, 17)
     }
 
     #[test]
     fn test_2() {
+        let data = fs::read_to_string(INPUT).unwrap();
+        assert_eq!(solve_2(&data).unwrap(), "EAHKRECP");
+
+        // The example folds into a square, which is not a letter.
         let data = fs::read_to_string(INPUT_TEST).unwrap();
-        solve_2(&data);
+        assert!(solve_2(&data).is_err());
     }
 
     #[test]
@@ -209,17 +878,323 @@ mod tests {
         assert_eq!(d, Dot { x: 8, y: 4 });
     }
     #[test]
-    fn test_dot_fold_from_str() {
+    fn test_fold_from_str() {
         let s = "20,12";
         let mut d = s.parse::<Dot>().unwrap();
         assert_eq!(d, Dot { x: 20, y: 12 });
 
-        let s1 = "fold along y=7";
-        let s2 = "fold along x=5";
+        let f1 = "fold along y=7".parse::<Fold>().unwrap();
+        let f2 = "fold along x=5".parse::<Fold>().unwrap();
+        assert_eq!((f1, f2), (Fold::Y(7), Fold::X(5)));
+        assert_eq!(f1.to_string(), "fold along y=7");
+
+        d = f1.apply(d);
+        d = f2.apply(d);
+
+        assert_eq!(d, Dot { x: -10, y: 2 });
+
+        assert!("fold along z=3".parse::<Fold>().is_err());
+        assert!("fold y=3".parse::<Fold>().is_err());
+        assert!("fold along y=".parse::<Fold>().is_err());
+    }
+
+    #[test]
+    fn test_paper_from_str() {
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        let paper = data.parse::<Paper>().unwrap();
+        assert_eq!(paper.len(), 18);
+        assert_eq!(paper.folds(), &[Fold::Y(7), Fold::X(5)]);
+
+        let err = "1,2\n3;4\n\nfold along x=1".parse::<Paper>().unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+        let err = "1,2\n\nfold along x=1\nfold along q=1"
+            .parse::<Paper>()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 4"));
+    }
+
+    #[test]
+    fn test_paper_fold() {
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        let paper = data.parse::<Paper>().unwrap();
+
+        let once = paper.fold_next().unwrap();
+        assert_eq!(once.len(), 17);
+        assert_eq!(once.folds(), &[Fold::X(5)]);
+        assert_eq!(once, paper.fold_all(Some(1)));
+        assert_eq!(once.dots(), paper.fold(&Fold::Y(7)).dots());
+
+        let done = once.fold_next().unwrap();
+        assert_eq!(done.len(), 16);
+        assert!(done.fold_next().is_none());
+        assert_eq!(done, paper.fold_all(None));
+    }
+
+    #[test]
+    fn test_render() {
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        let paper = data.parse::<Paper>().unwrap().fold_all(None);
+        assert_eq!(
+            paper.render(Glyphs::default()),
+            "#####\n#...#\n#...#\n#...#\n#####\n"
+        );
+
+        let dots: HashSet<Dot> = vec![Dot { x: -1, y: -1 }, Dot { x: 1, y: 0 }]
+            .into_iter()
+            .collect();
+        let glyphs = Glyphs {
+            on: '█', off: ' '
+        };
+        assert_eq!(render(&dots, glyphs), "█  \n  █\n");
+        assert_eq!(render(&HashSet::new(), glyphs), "");
+        assert_eq!(
+            bounds(&dots),
+            Some((Dot { x: -1, y: -1 }, Dot { x: 1, y: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_write_pbm() {
+        let dots: HashSet<Dot> = vec![Dot { x: -1, y: -1 }, Dot { x: 1, y: 0 }]
+            .into_iter()
+            .collect();
+        let mut out = vec![];
+        write_pbm(&dots, &mut out, 1).unwrap();
+        assert_eq!(out, b"P4\n3 2\n\x80\x20");
+
+        let data = fs::read_to_string(INPUT).unwrap();
+        let paper = data.parse::<Paper>().unwrap().fold_all(None);
+        let mut out = vec![];
+        write_pbm(paper.dots(), &mut out, 4).unwrap();
+        // 39 x 6 letters at scale 4, rows padded to 20 bytes.
+        assert_eq!(&out[..10], b"P4\n156 24\n");
+        assert_eq!(out.len(), 10 + 24 * 20);
+        // Each letter row is repeated for every scaled pixel row.
+        assert_eq!(&out[10..30], &out[70..90]);
+        // The top bar of the leading 'E'.
+        assert_eq!(&out[10..12], &[0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_transform() {
+        for input in [INPUT_TEST, INPUT].iter() {
+            let paper = fs::read_to_string(input).unwrap().parse::<Paper>().unwrap();
+            let transform = paper.transform();
+            let folded: HashSet<Dot> = paper.dots().iter().map(|d| transform.apply(d)).collect();
+            assert_eq!(&folded, paper.fold_all(None).dots());
+
+            for d in paper.dots() {
+                assert!(transform.preimage(&transform.apply(d)).contains(d));
+            }
+        }
+
+        // Outside the tabulated extent the folds are applied directly.
+        let transform = Transform::new(
+            &[Fold::Y(7), Fold::X(5)],
+            &Dot { x: 0, y: 0 },
+            &Dot { x: 10, y: 14 },
+        );
+        assert_eq!(transform.apply(&Dot { x: 20, y: 12 }), Dot { x: -10, y: 2 });
+    }
+
+    #[test]
+    fn test_preimage() {
+        let transform = Transform::new(
+            &[Fold::Y(7), Fold::X(5)],
+            &Dot { x: 0, y: 0 },
+            &Dot { x: 10, y: 14 },
+        );
+        let mut originals = transform.preimage(&Dot { x: 0, y: 0 });
+        originals.sort_by_key(|d| (d.x, d.y));
+        assert_eq!(
+            originals,
+            vec![
+                Dot { x: 0, y: 0 },
+                Dot { x: 0, y: 14 },
+                Dot { x: 10, y: 0 },
+                Dot { x: 10, y: 14 },
+            ]
+        );
+        assert_eq!(transform.preimage(&Dot { x: 5, y: 7 }).len(), 1);
+        assert!(transform.preimage(&Dot { x: 6, y: 0 }).is_empty());
+    }
+
+    #[test]
+    fn test_validate() {
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        assert!(data.parse::<Paper>().unwrap().validate().is_empty());
+
+        let paper = "20,12\n3,7\n\nfold along y=7\nfold along x=5"
+            .parse::<Paper>()
+            .unwrap();
+        let issues = paper.validate();
+        assert_eq!(
+            issues,
+            vec![
+                FoldIssue::OnFoldLine {
+                    step: 0,
+                    fold: Fold::Y(7),
+                    dots: vec![Dot { x: 3, y: 7 }],
+                },
+                FoldIssue::BeyondEdge {
+                    step: 1,
+                    fold: Fold::X(5),
+                    dots: vec![Dot { x: 20, y: 2 }],
+                },
+            ]
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "fold 2 (fold along x=5): 1 dot(s) folded past the edge"
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        let paper = data.parse::<Paper>().unwrap();
+        let mut history = History::new(paper.clone());
+
+        let step = history.fold_next().unwrap();
+        assert_eq!(step.fold, Fold::Y(7));
+        // 18 dots become 17, so exactly one pair merged.
+        assert_eq!(
+            step.merged,
+            vec![(
+                Dot { x: 8, y: 4 },
+                vec![Dot { x: 8, y: 4 }, Dot { x: 8, y: 10 }]
+            )]
+        );
+        assert_eq!(history.paper().len(), 17);
+
+        let step = history.fold_next().unwrap();
+        let lost: usize = step.merged.iter().map(|(_, from)| from.len() - 1).sum();
+        assert_eq!(lost, 1);
+        assert!(history.fold_next().is_none());
+        assert_eq!(history.steps().len(), 2);
+
+        assert_eq!(history.undo(), Some(Fold::X(5)));
+        assert_eq!(history.paper(), &paper.fold_all(Some(1)));
+        assert_eq!(history.undo(), Some(Fold::Y(7)));
+        assert_eq!(history.paper(), &paper);
+        assert_eq!(history.undo(), None);
+    }
+
+    /// Pseudo random dots covering about a quarter of a `size` x `size` sheet.
+    fn noise(size: isize, seed: u64) -> HashSet<Dot> {
+        let mut state = seed;
+        let mut dots = HashSet::new();
+        for y in 0..size {
+            for x in 0..size {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                if state >> 62 == 0 {
+                    dots.insert(Dot { x, y });
+                }
+            }
+        }
+        dots
+    }
+
+    #[test]
+    fn test_bitmap() {
+        let data = fs::read_to_string(INPUT).unwrap();
+        let paper = data.parse::<Paper>().unwrap();
+        let mut bitmap = Bitmap::from_dots(paper.dots()).unwrap();
+        assert_eq!(&bitmap.dots(), paper.dots());
+        for (i, fold) in paper.folds().iter().enumerate() {
+            bitmap = bitmap.fold(fold).unwrap();
+            assert_eq!(&bitmap.dots(), paper.fold_all(Some(i + 1)).dots());
+        }
+
+        // Odd sizes, lines off the middle and dots on the line.
+        let dots = noise(131, 7);
+        for fold in [Fold::X(65), Fold::X(100), Fold::Y(66), Fold::Y(130)].iter() {
+            let expected: HashSet<Dot> = dots.iter().map(|d| fold.apply(d.clone())).collect();
+            let bitmap = Bitmap::from_dots(&dots).unwrap().fold(fold).unwrap();
+            assert_eq!(bitmap.dots(), expected);
+            assert_eq!(bitmap.len(), expected.len());
+        }
+        assert!(Bitmap::from_dots(&dots)
+            .unwrap()
+            .fold(&Fold::X(20))
+            .is_none());
+
+        let negative: HashSet<Dot> = vec![Dot { x: -3, y: -3 }].into_iter().collect();
+        assert!(Bitmap::from_dots(&negative).is_none());
+        let mixed: HashSet<Dot> = vec![Dot { x: 4, y: -1 }, Dot { x: 2, y: 2 }]
+            .into_iter()
+            .collect();
+        assert!(Bitmap::from_dots(&mixed).is_none());
+        assert!(Bitmap::from_dots(&HashSet::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sheet() {
+        let data = fs::read_to_string(INPUT_TEST).unwrap();
+        let paper = data.parse::<Paper>().unwrap();
+        assert!(paper.sheet().is_dense());
+
+        let sparse = vec![Dot { x: 0, y: 0 }, Dot { x: 1000, y: 1000 }];
+        let sheet = Sheet::new(sparse.into_iter().collect());
+        assert!(!sheet.is_dense());
+
+        // The area does not fit in a usize.
+        let far = isize::MAX;
+        let sparse = vec![Dot { x: 0, y: 0 }, Dot { x: far, y: far }];
+        let sheet = Sheet::new(sparse.into_iter().collect());
+        assert!(!sheet.is_dense());
+        let data = "5000000000,5000000000\n0,0\n\nfold along x=1\n";
+        assert_eq!(solve_1(data, None).unwrap(), 2);
+
+        // Folding past the edge falls back to the dots.
+        let sheet = paper.sheet().fold(&Fold::X(2));
+        assert_eq!(sheet.dots(), paper.fold(&Fold::X(2)).dots().clone());
+        assert!(!sheet.is_dense());
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture bench_fold`.
+    #[test]
+    #[ignore]
+    fn bench_fold() {
+        use std::time::Instant;
+
+        let folds: Vec<Fold> = (1..12)
+            .map(|k| (4095 >> k) as isize)
+            .flat_map(|l| vec![Fold::X(l), Fold::Y(l)])
+            .collect();
+        // Like the puzzle, never put a dot on a fold line.
+        let mut dots = noise(4095, 13);
+        dots.retain(|d| {
+            folds
+                .iter()
+                .try_fold(d.clone(), |d, f| match f.is_on_line(&d) {
+                    true => None,
+                    false => Some(f.apply(d)),
+                })
+                .is_some()
+        });
+
+        let start = Instant::now();
+        let hashed = folds.iter().fold(dots.clone(), |dots, f| {
+            dots.into_iter().map(|d| f.apply(d)).collect()
+        });
+        let hashed_time = start.elapsed();
 
-        d = d.fold_from_str(s1).unwrap();
-        d = d.fold_from_str(s2).unwrap();
+        let start = Instant::now();
+        let bitmap = folds
+            .iter()
+            .fold(Bitmap::from_dots(&dots).unwrap(), |b, f| b.fold(f).unwrap());
+        let bitmap_time = start.elapsed();
 
-        assert_eq!(d, Dot { x: -10, y: 2 })
+        assert_eq!(bitmap.dots(), hashed);
+        println!(
+            "{} dots, {} folds: HashSet {:?}, Bitmap {:?}",
+            dots.len(),
+            folds.len(),
+            hashed_time,
+            bitmap_time
+        );
     }
 }
@@ -1,10 +1,13 @@
 #![allow(dead_code, unused)]
 use std::boxed::Box;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::fs::read_to_string;
+use std::hash::Hash;
 use std::str::FromStr;
 
+use crate::bigint::{BigUint, Count};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Pair {
     first: char,
@@ -22,27 +25,172 @@ struct Rules {
     rules: HashMap<Pair, char>,
 }
 
+/// Why a line of the rules section was rejected. Rules are parsed without
+/// the template, so lines count from 1 at the start of the rules section,
+/// not of the input file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RulesError {
+    Malformed {
+        line: usize,
+        text: String,
+    },
+    Duplicate {
+        line: usize,
+        pair: Pair,
+        first: usize,
+    },
+    MultiCharOutput {
+        line: usize,
+        output: String,
+    },
+}
+
+impl Display for RulesError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            RulesError::Malformed { line, text } => {
+                write!(
+                    f,
+                    "rule line {}: expected 'AB -> C', found '{}'",
+                    line, text
+                )
+            }
+            RulesError::Duplicate { line, pair, first } => {
+                write!(
+                    f,
+                    "rule line {}: {} already has a rule on rule line {}",
+                    line, pair, first
+                )
+            }
+            RulesError::MultiCharOutput { line, output } => {
+                write!(
+                    f,
+                    "rule line {}: '{}' is more than one element",
+                    line, output
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
 impl FromStr for Rules {
-    type Err = ();
+    type Err = RulesError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut rules = HashMap::new();
-        for line in s.lines() {
-            let mut parts = line.split(" -> ");
-            let first = parts.next().ok_or(())?;
-            let second = parts.next().ok_or(())?;
+        let mut defined_on = HashMap::new();
+        for (i, line) in s.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let malformed = || RulesError::Malformed {
+                line: i + 1,
+                text: line.to_string(),
+            };
+            let (first, second) = line.trim().split_once(" -> ").ok_or_else(malformed)?;
             let first = first.chars().collect::<Vec<_>>();
             let second = second.chars().collect::<Vec<_>>();
-            let first = Pair {
-                first: *first.get(0).ok_or(())?,
-                second: *first.get(1).ok_or(())?,
+            let first = match first[..] {
+                [a, b] => Pair {
+                    first: a,
+                    second: b,
+                },
+                _ => return Err(malformed()),
+            };
+            let second = match second[..] {
+                [c] => c,
+                [] => return Err(malformed()),
+                _ => {
+                    return Err(RulesError::MultiCharOutput {
+                        line: i + 1,
+                        output: second.iter().collect(),
+                    })
+                }
             };
-            let second = *second.get(0).ok_or(())?;
+            if let Some(&first_line) = defined_on.get(&first) {
+                return Err(RulesError::Duplicate {
+                    line: i + 1,
+                    pair: first,
+                    first: first_line,
+                });
+            }
+            defined_on.insert(first, i + 1);
             rules.insert(first, second);
         }
         Ok(Rules { rules })
     }
 }
 
+/// Which rules matter for a template, see `Rules::coverage`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Coverage {
+    /// Pairs that can appear but have no rule, so never change.
+    missing: Vec<Pair>,
+    /// Rules for pairs that never appear.
+    unreachable: Vec<Pair>,
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let list = |pairs: &[Pair]| {
+            pairs
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(f, "pairs without a rule: {}", list(&self.missing))?;
+        write!(f, "unreachable rules: {}", list(&self.unreachable))
+    }
+}
+
+impl Rules {
+    /// Follows the rules from the pairs of `template` to find every pair that
+    /// can ever appear.
+    fn coverage(&self, template: &str) -> Coverage {
+        let mut reachable = HashSet::new();
+        let mut stack: Vec<Pair> = template
+            .chars()
+            .zip(template.chars().skip(1))
+            .map(|(first, second)| Pair { first, second })
+            .collect();
+        while let Some(pair) = stack.pop() {
+            if !reachable.insert(pair) {
+                continue;
+            }
+            if let Some(&c) = self.rules.get(&pair) {
+                stack.push(Pair {
+                    first: pair.first,
+                    second: c,
+                });
+                stack.push(Pair {
+                    first: c,
+                    second: pair.second,
+                });
+            }
+        }
+
+        let sorted = |mut pairs: Vec<Pair>| {
+            pairs.sort_by_key(|p| (p.first, p.second));
+            pairs
+        };
+        Coverage {
+            missing: sorted(
+                reachable
+                    .iter()
+                    .filter(|p| !self.rules.contains_key(p))
+                    .copied()
+                    .collect(),
+            ),
+            unreachable: sorted(
+                self.rules
+                    .keys()
+                    .filter(|p| !reachable.contains(p))
+                    .copied()
+                    .collect(),
+            ),
+        }
+    }
+}
+
 impl Display for Rules {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         let mut rules = self.rules.iter();
@@ -56,13 +204,19 @@ impl Display for Rules {
     }
 }
 
+/// Pair and element counts.
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Polymer {
-    pairs: HashMap<Pair, usize>,
-    elements: HashMap<char, usize>,
+struct Polymer<C: Count = usize> {
+    pairs: HashMap<Pair, C>,
+    elements: HashMap<char, C>,
 }
 
-impl FromStr for Polymer {
+fn increment<K: Eq + Hash, C: Count>(map: &mut HashMap<K, C>, key: K, count: C) {
+    let entry = map.entry(key).or_insert_with(C::zero);
+    *entry = entry.clone() + count;
+}
+
+impl<C: Count> FromStr for Polymer<C> {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut pairs = HashMap::new();
@@ -71,27 +225,30 @@ impl FromStr for Polymer {
                 first: c.0,
                 second: c.1,
             };
-            *pairs.entry(pair).or_insert(0) += 1;
+            increment(&mut pairs, pair, C::from_usize(1));
         }
 
         let mut elements = HashMap::new();
         for c in s.chars() {
-            *elements.entry(c).or_insert(0) += 1;
+            increment(&mut elements, c, C::from_usize(1));
         }
 
         Ok(Polymer { pairs, elements })
     }
 }
 
-impl Polymer {
+impl<C: Count> Polymer<C> {
     fn polymerize(&self, rules: &HashMap<Pair, char>) -> Self {
         let mut polymer = self.clone();
 
         for (pair, count) in self.pairs.iter() {
             if let Some(new_element) = rules.get(pair) {
-                polymer.pairs.entry(*pair).and_modify(|c| *c -= count);
+                polymer
+                    .pairs
+                    .entry(*pair)
+                    .and_modify(|c| *c = c.clone() - count.clone());
 
-                if polymer.pairs.get(pair).unwrap_or(&0) == &0 {
+                if polymer.pairs.get(pair).is_none_or(|c| c.is_zero()) {
                     polymer.pairs.remove(pair);
                 }
 
@@ -99,36 +256,335 @@ impl Polymer {
                     first: *new_element,
                     second: pair.second,
                 };
-                *polymer.pairs.entry(new_pair).or_insert(0) += count;
+                increment(&mut polymer.pairs, new_pair, count.clone());
                 let new_pair = Pair {
                     first: pair.first,
                     second: *new_element,
                 };
-                *polymer.pairs.entry(new_pair).or_insert(0) += count;
+                increment(&mut polymer.pairs, new_pair, count.clone());
 
-                *polymer.elements.entry(*new_element).or_insert(0) += count;
+                increment(&mut polymer.elements, *new_element, count.clone());
             }
         }
 
         polymer
     }
 
-    fn solution(&self) -> Option<usize> {
-        Some(self.elements.values().max()? - self.elements.values().min()?)
+    fn solution(&self) -> Option<C> {
+        Some(self.elements.values().max()?.clone() - self.elements.values().min()?.clone())
     }
 }
 
-impl Display for Polymer {
+impl<C: Count> Display for Polymer<C> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         for (key, val) in self.pairs.iter() {
-            if *val != 0 {
+            if !val.is_zero() {
                 write!(f, "{} -> {}, ", key, val)?;
             }
-        };
+        }
         Ok(())
     }
 }
 
+const ALPHABET: usize = 26;
+const NUM_PAIRS: usize = ALPHABET * ALPHABET;
+
+fn element_index(c: char) -> Option<usize> {
+    match c {
+        'A'..='Z' => Some(c as usize - 'A' as usize),
+        _ => None,
+    }
+}
+
+fn element_char(i: usize) -> char {
+    (b'A' + i as u8) as char
+}
+
+fn pair_index(pair: &Pair) -> Option<usize> {
+    Some(element_index(pair.first)? * ALPHABET + element_index(pair.second)?)
+}
+
+/// Insertion rules by pair index: the two pairs a pair turns into and the
+/// index of the inserted element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DenseRules {
+    children: [Option<(usize, usize, usize)>; NUM_PAIRS],
+}
+
+impl DenseRules {
+    /// `None` unless all elements are capital letters.
+    fn new(rules: &Rules) -> Option<Self> {
+        let mut children = [None; NUM_PAIRS];
+        for (pair, insert) in rules.rules.iter() {
+            let (first, second) = (element_index(pair.first)?, element_index(pair.second)?);
+            let insert = element_index(*insert)?;
+            children[first * ALPHABET + second] = Some((
+                first * ALPHABET + insert,
+                insert * ALPHABET + second,
+                insert,
+            ));
+        }
+        Some(DenseRules { children })
+    }
+}
+
+/// `Polymer` with counts in arrays indexed by element and pair index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DensePolymer {
+    pairs: [usize; NUM_PAIRS],
+    elements: [usize; ALPHABET],
+}
+
+impl FromStr for DensePolymer {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s
+            .chars()
+            .map(element_index)
+            .collect::<Option<Vec<_>>>()
+            .ok_or(())?;
+
+        let mut polymer = DensePolymer {
+            pairs: [0; NUM_PAIRS],
+            elements: [0; ALPHABET],
+        };
+        for (first, second) in elements.iter().zip(elements.iter().skip(1)) {
+            polymer.pairs[first * ALPHABET + second] += 1;
+        }
+        for e in elements {
+            polymer.elements[e] += 1;
+        }
+        Ok(polymer)
+    }
+}
+
+impl DensePolymer {
+    fn polymerize(&self, rules: &DenseRules) -> Self {
+        let mut polymer = DensePolymer {
+            pairs: [0; NUM_PAIRS],
+            elements: self.elements,
+        };
+        for (i, &count) in self.pairs.iter().enumerate().filter(|(_, &c)| c > 0) {
+            match rules.children[i] {
+                Some((left, right, insert)) => {
+                    polymer.pairs[left] += count;
+                    polymer.pairs[right] += count;
+                    polymer.elements[insert] += count;
+                }
+                None => polymer.pairs[i] += count,
+            }
+        }
+        polymer
+    }
+
+    fn solution(&self) -> Option<usize> {
+        let present = self.elements.iter().filter(|&&c| c > 0);
+        Some(present.clone().max()? - present.min()?)
+    }
+
+    fn to_polymer(&self) -> Polymer {
+        let pairs = self
+            .pairs
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c > 0)
+            .map(|(i, &c)| {
+                let pair = Pair {
+                    first: element_char(i / ALPHABET),
+                    second: element_char(i % ALPHABET),
+                };
+                (pair, c)
+            })
+            .collect();
+        let elements = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c > 0)
+            .map(|(i, &c)| (element_char(i), c))
+            .collect();
+        Polymer { pairs, elements }
+    }
+}
+
+/// Pair and element counts modulo `modulus`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ModPolymer {
+    modulus: u64,
+    pairs: [u64; NUM_PAIRS],
+    elements: [u64; ALPHABET],
+}
+
+fn add_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 + b as u128) % modulus as u128) as u64
+}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    (a as u128 * b as u128 % modulus as u128) as u64
+}
+
+fn mat_mul(a: &[Vec<u64>], b: &[Vec<u64>], modulus: u64) -> Vec<Vec<u64>> {
+    let n = a.len();
+    let mut c = vec![vec![0; n]; n];
+    for i in 0..n {
+        for k in (0..n).filter(|&k| a[i][k] != 0) {
+            for j in 0..n {
+                c[i][j] = add_mod(c[i][j], mul_mod(a[i][k], b[k][j], modulus), modulus);
+            }
+        }
+    }
+    c
+}
+
+/// The linear map from the counts of all pairs and elements reachable from a
+/// template to their counts one step later. The first `pairs.len()` states are
+/// pairs, the rest elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Transition {
+    pairs: Vec<usize>,
+    elements: Vec<usize>,
+    /// `matrix[to][from]` is how many of state `to` one `from` turns into.
+    matrix: Vec<Vec<u64>>,
+}
+
+impl Transition {
+    fn new(polymer: &DensePolymer, rules: &DenseRules) -> Self {
+        let mut reachable = [false; NUM_PAIRS];
+        let mut stack: Vec<usize> = (0..NUM_PAIRS).filter(|&i| polymer.pairs[i] > 0).collect();
+        let mut used = polymer.elements.map(|c| c > 0);
+        while let Some(i) = stack.pop() {
+            if reachable[i] {
+                continue;
+            }
+            reachable[i] = true;
+            if let Some((left, right, insert)) = rules.children[i] {
+                used[insert] = true;
+                stack.push(left);
+                stack.push(right);
+            }
+        }
+        let pairs: Vec<usize> = (0..NUM_PAIRS).filter(|&i| reachable[i]).collect();
+        let elements: Vec<usize> = (0..ALPHABET).filter(|&e| used[e]).collect();
+
+        let n = pairs.len() + elements.len();
+        let pair_state = |i: usize| pairs.binary_search(&i).unwrap();
+        let element_state = |e: usize| pairs.len() + elements.binary_search(&e).unwrap();
+        let mut matrix = vec![vec![0; n]; n];
+        for (from, &i) in pairs.iter().enumerate() {
+            match rules.children[i] {
+                Some((left, right, insert)) => {
+                    matrix[pair_state(left)][from] += 1;
+                    matrix[pair_state(right)][from] += 1;
+                    matrix[element_state(insert)][from] += 1;
+                }
+                None => matrix[from][from] += 1,
+            }
+        }
+        for (from, row) in matrix.iter_mut().enumerate().skip(pairs.len()) {
+            row[from] += 1;
+        }
+        Transition {
+            pairs,
+            elements,
+            matrix,
+        }
+    }
+
+    /// The matrix to the power `steps`, by repeated squaring.
+    fn power(&self, mut steps: u64, modulus: u64) -> Vec<Vec<u64>> {
+        let n = self.matrix.len();
+        let mut result: Vec<Vec<u64>> = (0..n)
+            .map(|i| (0..n).map(|j| (i == j) as u64 % modulus).collect())
+            .collect();
+        let mut base: Vec<Vec<u64>> = self
+            .matrix
+            .iter()
+            .map(|row| row.iter().map(|&x| x % modulus).collect())
+            .collect();
+        while steps > 0 {
+            if steps & 1 == 1 {
+                result = mat_mul(&result, &base, modulus);
+            }
+            steps >>= 1;
+            if steps > 0 {
+                base = mat_mul(&base, &base, modulus);
+            }
+        }
+        result
+    }
+
+    /// Counts after `steps` steps starting from `polymer`, which must be the
+    /// template the transition was built from or reachable from it.
+    fn apply(&self, polymer: &DensePolymer, steps: u64, modulus: u64) -> ModPolymer {
+        let start: Vec<u64> = self
+            .pairs
+            .iter()
+            .map(|&i| polymer.pairs[i] as u64 % modulus)
+            .chain(
+                self.elements
+                    .iter()
+                    .map(|&e| polymer.elements[e] as u64 % modulus),
+            )
+            .collect();
+        let power = self.power(steps, modulus);
+        let counts: Vec<u64> = power
+            .iter()
+            .map(|row| {
+                row.iter().zip(start.iter()).fold(0, |acc, (&a, &b)| {
+                    add_mod(acc, mul_mod(a, b, modulus), modulus)
+                })
+            })
+            .collect();
+
+        let mut result = ModPolymer {
+            modulus,
+            pairs: [0; NUM_PAIRS],
+            elements: [0; ALPHABET],
+        };
+        for (state, &i) in self.pairs.iter().enumerate() {
+            result.pairs[i] = counts[state];
+        }
+        for (state, &e) in self.elements.iter().enumerate() {
+            result.elements[e] = counts[self.pairs.len() + state];
+        }
+        result
+    }
+}
+
+impl DensePolymer {
+    /// Counts modulo `modulus` after `steps` steps, in time logarithmic in
+    /// `steps`.
+    fn polymerize_mod(&self, rules: &DenseRules, steps: u64, modulus: u64) -> ModPolymer {
+        assert!(modulus > 0, "Modulus must be positive.");
+        Transition::new(self, rules).apply(self, steps, modulus)
+    }
+}
+
+#[cfg(test)]
+const TEST_INPUT: &str = "./input/day14_test.txt";
+
+/// The template parsed as `P` and the rules of an input file.
+#[cfg(test)]
+fn read_input<P: FromStr>(path: &str) -> (P, Rules)
+where
+    P::Err: std::fmt::Debug,
+{
+    let data = read_to_string(path).expect("Failed to read input");
+
+    let mut parts = data.split("\n\n");
+    let polymer = parts
+        .next()
+        .expect("Failed to get polymer.")
+        .parse()
+        .expect("Could not parse Polymer.");
+    let rules = parts
+        .next()
+        .expect("Failed to get rules.")
+        .parse()
+        .expect("Could not parse Rules.");
+    (polymer, rules)
+}
+
 #[cfg(test)]
 mod test_rules {
     use super::*;
@@ -162,6 +618,77 @@ mod test_rules {
             Some(&'C')
         );
     }
+    #[test]
+    fn test_rules_errors() {
+        assert_eq!(
+            "CH -> B\n\nHH -> N\nCH -> C".parse::<Rules>(),
+            Err(RulesError::Duplicate {
+                line: 4,
+                pair: Pair {
+                    first: 'C',
+                    second: 'H'
+                },
+                first: 1,
+            })
+        );
+        assert_eq!(
+            "CH -> B\nHHH -> N".parse::<Rules>(),
+            Err(RulesError::Malformed {
+                line: 2,
+                text: "HHH -> N".to_string(),
+            })
+        );
+        assert!(matches!(
+            "CH => B".parse::<Rules>(),
+            Err(RulesError::Malformed { line: 1, .. })
+        ));
+        assert!(matches!(
+            "CH -> ".parse::<Rules>(),
+            Err(RulesError::Malformed { line: 1, .. })
+        ));
+        let err = "CH -> BN".parse::<Rules>().unwrap_err();
+        assert_eq!(
+            err,
+            RulesError::MultiCharOutput {
+                line: 1,
+                output: "BN".to_string(),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "rule line 1: 'BN' is more than one element"
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let data = read_to_string(TEST_INPUT).expect("Failed to read input");
+        let mut parts = data.split("\n\n");
+        let template = parts.next().expect("Failed to get polymer");
+        let rules: Rules = parts
+            .next()
+            .expect("Failed to get rules")
+            .parse()
+            .expect("Could not parse Rules.");
+
+        // The example has a rule for all 16 pairs of its 4 elements.
+        let coverage = rules.coverage(template);
+        assert!(coverage.missing.is_empty());
+        assert!(coverage.unreachable.is_empty());
+
+        let rules: Rules = "AB -> C\nCB -> B\nXY -> Z".parse().unwrap();
+        let coverage = rules.coverage("AB");
+        let pair = |s: &str| Pair {
+            first: s.chars().next().unwrap(),
+            second: s.chars().nth(1).unwrap(),
+        };
+        assert_eq!(coverage.missing, vec![pair("AC"), pair("BB")]);
+        assert_eq!(coverage.unreachable, vec![pair("XY")]);
+        assert_eq!(
+            coverage.to_string(),
+            "pairs without a rule: AC, BB\nunreachable rules: XY"
+        );
+    }
 }
 
 fn part_1() {
@@ -191,7 +718,7 @@ fn part_2() {
     let data = read_to_string("./input/day14.txt").expect("Failed to read input");
 
     let mut parts = data.split("\n\n");
-    let mut polymer: Polymer = parts
+    let mut polymer: DensePolymer = parts
         .next()
         .expect("Failed to get polymer")
         .parse()
@@ -202,11 +729,12 @@ fn part_2() {
         .expect("Failed to get rules")
         .parse()
         .expect("Could not parse Rules.");
+    let rules = DenseRules::new(&rules).expect("Elements must be capital letters.");
 
     for _ in 0..40 {
-        polymer = polymer.polymerize(&rules.rules);
+        polymer = polymer.polymerize(&rules);
     }
-    println!("{}", polymer);
+    println!("{}", polymer.to_polymer());
     println!("{}", polymer.solution().unwrap());
 }
 
@@ -364,3 +892,126 @@ mod test_polymer {
         part_2()
     }
 }
+
+#[cfg(test)]
+mod test_dense_polymer {
+    use super::*;
+
+    #[test]
+    fn test_dense_from_str() {
+        let (polymer, _): (Polymer, _) = read_input(TEST_INPUT);
+        let (dense, _): (DensePolymer, _) = read_input(TEST_INPUT);
+        assert_eq!(dense.to_polymer(), polymer);
+        assert!("NNcB".parse::<DensePolymer>().is_err());
+    }
+
+    #[test]
+    fn test_dense_matches_polymer() {
+        for path in [TEST_INPUT, "./input/day14.txt"].iter() {
+            let (mut polymer, rules): (Polymer, _) = read_input(path);
+            let (mut dense, _): (DensePolymer, _) = read_input(path);
+            let dense_rules = DenseRules::new(&rules).expect("Could not index Rules.");
+            for _ in 0..10 {
+                polymer = polymer.polymerize(&rules.rules);
+                dense = dense.polymerize(&dense_rules);
+                assert_eq!(dense.to_polymer(), polymer);
+                assert_eq!(dense.solution(), polymer.solution());
+            }
+        }
+    }
+
+    #[test]
+    fn test_dense_part_2() {
+        let (mut dense, rules): (DensePolymer, _) = read_input(TEST_INPUT);
+        let rules = DenseRules::new(&rules).expect("Could not index Rules.");
+        for _ in 0..10 {
+            dense = dense.polymerize(&rules);
+        }
+        assert_eq!(dense.solution(), Some(1588));
+        for _ in 10..40 {
+            dense = dense.polymerize(&rules);
+        }
+        assert_eq!(dense.solution(), Some(2188189693529));
+    }
+}
+
+#[cfg(test)]
+mod test_transition {
+    use super::*;
+
+    /// Large enough that no count up to 40 steps is reduced.
+    const PRIME: u64 = (1 << 61) - 1;
+
+    fn read(path: &str) -> (DensePolymer, DenseRules) {
+        let (polymer, rules) = read_input(path);
+        (
+            polymer,
+            DenseRules::new(&rules).expect("Could not index Rules."),
+        )
+    }
+
+    #[test]
+    fn test_matches_stepping() {
+        for path in [TEST_INPUT, "./input/day14.txt"].iter() {
+            let (start, rules) = read(path);
+            let mut polymer = start.clone();
+            for steps in 0..=40 {
+                if [0, 1, 2, 10, 31, 40].contains(&steps) {
+                    let counts = start.polymerize_mod(&rules, steps, PRIME);
+                    assert_eq!(counts.pairs, polymer.pairs.map(|c| c as u64));
+                    assert_eq!(counts.elements, polymer.elements.map(|c| c as u64));
+                }
+                polymer = polymer.polymerize(&rules);
+            }
+        }
+    }
+
+    #[test]
+    fn test_huge_steps() {
+        let (start, rules) = read("./input/day14.txt");
+        let steps = 1_000_000_000_000;
+
+        // Sums of counts near the largest prime below 2^64 overflow a `u64`.
+        for &modulus in [1_000_000_007, u64::MAX - 58].iter() {
+            // One more step by hand, reduced, must agree with the next power.
+            let counts = start.polymerize_mod(&rules, steps, modulus);
+            let mut next = ModPolymer {
+                modulus,
+                pairs: [0; NUM_PAIRS],
+                elements: counts.elements,
+            };
+            for (i, &count) in counts.pairs.iter().enumerate() {
+                match rules.children[i] {
+                    Some((left, right, insert)) => {
+                        next.pairs[left] = add_mod(next.pairs[left], count, modulus);
+                        next.pairs[right] = add_mod(next.pairs[right], count, modulus);
+                        next.elements[insert] = add_mod(next.elements[insert], count, modulus);
+                    }
+                    None => next.pairs[i] = add_mod(next.pairs[i], count, modulus),
+                }
+            }
+            assert_eq!(start.polymerize_mod(&rules, steps + 1, modulus), next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_big_polymer {
+    use super::*;
+
+    #[test]
+    fn test_big_polymer() {
+        let (mut polymer, rules): (Polymer<BigUint>, _) = read_input(TEST_INPUT);
+        for _ in 0..40 {
+            polymer = polymer.polymerize(&rules.rules);
+        }
+        assert_eq!(polymer.solution(), Some(BigUint::from(2188189693529u64)));
+
+        // Past the range of a `u128` the counts stay exact.
+        for _ in 40..200 {
+            polymer = polymer.polymerize(&rules.rules);
+        }
+        let u64_max = BigUint::from(u64::MAX);
+        assert!(polymer.solution().unwrap() > u64_max.clone() * u64_max);
+    }
+}
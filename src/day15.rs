@@ -1,4 +1,5 @@
 #![allow(dead_code)]
+use crate::export::Frame;
 use std::{fmt, str::FromStr, usize};
 
 type Index2D = (usize, usize);
@@ -115,6 +116,15 @@ impl Cavern {
     }
 }
 
+impl Cavern {
+    /// Risk levels as an image frame.
+    fn frame(&self) -> Frame {
+        let height = self.risk_level.len();
+        let width = self.risk_level.first().map_or(0, |row| row.len());
+        Frame::from_fn(width, height, |i, j| self.risk_level[i][j].min(255) as u8)
+    }
+}
+
 impl FromStr for Cavern {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -161,6 +171,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cavern_frame() {
+        use crate::export::{write_ppm, Ramp};
+
+        let input = fs::read_to_string(TEST_INPUT_FILE).unwrap();
+        let cavern = Cavern::from_str(&input).unwrap();
+        let mut out = vec![];
+        write_ppm(&mut out, &cavern.frame(), &Ramp::grayscale(10), 1).unwrap();
+
+        let header = b"P6\n10 10\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(out.len(), header.len() + 3 * 100);
+    }
+
     #[test]
     fn test_cavern_lowest_risk_path() {
         let input = fs::read_to_string(TEST_INPUT_FILE).unwrap();
@@ -0,0 +1,496 @@
+#![allow(dead_code)]
+//! Write grids of levels, like octopus energies or cavern risks, as images.
+//!
+//! Frames can be written as binary PBM (black and white), PGM (grayscale) or
+//! PPM (colour) files, or collected into an animated GIF. Each cell becomes a `scale` x `scale`
+//! block of pixels.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// A raster of levels, one per cell, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    levels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn from_fn<F: Fn(usize, usize) -> u8>(width: usize, height: usize, level: F) -> Self {
+        let mut levels = Vec::with_capacity(width * height);
+        for i in 0..height {
+            for j in 0..width {
+                levels.push(level(i, j));
+            }
+        }
+        Self {
+            width,
+            height,
+            levels,
+        }
+    }
+
+    fn level(&self, i: usize, j: usize) -> u8 {
+        self.levels[i * self.width + j]
+    }
+
+    /// The levels of the scaled image, row by row.
+    fn pixels(&self, scale: usize) -> impl Iterator<Item = u8> + '_ {
+        (0..self.height * scale).flat_map(move |y| {
+            (0..self.width * scale).map(move |x| self.level(y / scale, x / scale))
+        })
+    }
+}
+
+/// Colours for levels `0..len()`. Higher levels get the last colour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ramp {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Ramp {
+    /// `levels` colours spaced evenly along the straight lines between `stops`.
+    pub fn new(stops: &[[u8; 3]], levels: usize) -> Self {
+        assert!(!stops.is_empty() && levels > 0, "A ramp needs colours.");
+        let colors = (0..levels)
+            .map(|level| {
+                if levels == 1 || stops.len() == 1 {
+                    return stops[0];
+                }
+                // Position along the ramp, in units of the distance between stops.
+                let t = level as f64 * (stops.len() - 1) as f64 / (levels - 1) as f64;
+                let k = (t as usize).min(stops.len() - 2);
+                let frac = t - k as f64;
+                let mut color = [0; 3];
+                for (c, (a, b)) in color
+                    .iter_mut()
+                    .zip(stops[k].iter().zip(stops[k + 1].iter()))
+                {
+                    *c = (*a as f64 + frac * (*b as f64 - *a as f64)).round() as u8;
+                }
+                color
+            })
+            .collect();
+        Self { colors }
+    }
+
+    pub fn grayscale(levels: usize) -> Self {
+        Self::new(&[[0, 0, 0], [255, 255, 255]], levels)
+    }
+
+    /// Black through red and yellow to white.
+    pub fn heat(levels: usize) -> Self {
+        Self::new(
+            &[[0, 0, 0], [200, 0, 0], [255, 200, 0], [255, 255, 255]],
+            levels,
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    fn index(&self, level: u8) -> u8 {
+        (level as usize).min(self.colors.len() - 1) as u8
+    }
+
+    fn color(&self, level: u8) -> [u8; 3] {
+        self.colors[self.index(level) as usize]
+    }
+}
+
+/// Write a binary PBM. Cells with a non-zero level are black.
+pub fn write_pbm<W: Write>(out: &mut W, frame: &Frame, scale: usize) -> io::Result<()> {
+    let width = frame.width * scale;
+    write!(out, "P4\n{} {}\n", width, frame.height * scale)?;
+    let pixels: Vec<u8> = frame.pixels(scale).collect();
+    for row in pixels.chunks(width.max(1)) {
+        let mut bytes = vec![0u8; width.div_ceil(8)];
+        for (x, _) in row.iter().enumerate().filter(|(_, &l)| l > 0) {
+            bytes[x / 8] |= 0x80 >> (x % 8);
+        }
+        out.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Write a binary PGM, with `max_level` shown as white.
+pub fn write_pgm<W: Write>(
+    out: &mut W,
+    frame: &Frame,
+    max_level: u8,
+    scale: usize,
+) -> io::Result<()> {
+    let max_level = max_level.max(1);
+    write!(
+        out,
+        "P5\n{} {}\n{}\n",
+        frame.width * scale,
+        frame.height * scale,
+        max_level
+    )?;
+    let pixels: Vec<u8> = frame.pixels(scale).map(|l| l.min(max_level)).collect();
+    out.write_all(&pixels)
+}
+
+/// Write a binary PPM, colouring the levels with `ramp`.
+pub fn write_ppm<W: Write>(
+    out: &mut W,
+    frame: &Frame,
+    ramp: &Ramp,
+    scale: usize,
+) -> io::Result<()> {
+    write!(
+        out,
+        "P6\n{} {}\n255\n",
+        frame.width * scale,
+        frame.height * scale
+    )?;
+    let pixels: Vec<u8> = frame.pixels(scale).flat_map(|l| ramp.color(l)).collect();
+    out.write_all(&pixels)
+}
+
+/// Writes frames of the same size into an endlessly looping GIF.
+pub struct GifWriter<W: Write> {
+    out: W,
+    width: usize,
+    height: usize,
+    scale: usize,
+    ramp: Ramp,
+    /// Bits per palette index, the LZW minimum code size.
+    bits: u8,
+}
+
+impl<W: Write> GifWriter<W> {
+    pub fn new(
+        mut out: W,
+        width: usize,
+        height: usize,
+        scale: usize,
+        ramp: Ramp,
+    ) -> io::Result<Self> {
+        assert!(
+            ramp.len() <= 256,
+            "A GIF palette holds at most 256 colours."
+        );
+        let (w, h) = (width * scale, height * scale);
+        assert!(
+            w <= u16::MAX as usize && h <= u16::MAX as usize,
+            "Image too large for a GIF."
+        );
+
+        let mut bits = 2;
+        while 1 << bits < ramp.len() {
+            bits += 1;
+        }
+
+        out.write_all(b"GIF89a")?;
+        out.write_all(&(w as u16).to_le_bytes())?;
+        out.write_all(&(h as u16).to_le_bytes())?;
+        // Global colour table of 2^bits colours, no background, square pixels.
+        out.write_all(&[0x80 | ((bits - 1) << 4) | (bits - 1), 0, 0])?;
+        for i in 0..1 << bits {
+            out.write_all(&ramp.colors.get(i).copied().unwrap_or_default())?;
+        }
+        // Loop forever.
+        out.write_all(&[0x21, 0xFF, 11])?;
+        out.write_all(b"NETSCAPE2.0")?;
+        out.write_all(&[3, 1, 0, 0, 0])?;
+
+        Ok(Self {
+            out,
+            width,
+            height,
+            scale,
+            ramp,
+            bits,
+        })
+    }
+
+    /// Append a frame shown for `delay` hundredths of a second.
+    pub fn add_frame(&mut self, frame: &Frame, delay: u16) -> io::Result<()> {
+        assert!(
+            frame.width == self.width && frame.height == self.height,
+            "Frame size differs from the GIF's."
+        );
+        let (w, h) = (self.width * self.scale, self.height * self.scale);
+
+        // Graphic control extension carrying the delay.
+        self.out.write_all(&[0x21, 0xF9, 4, 0])?;
+        self.out.write_all(&delay.to_le_bytes())?;
+        self.out.write_all(&[0, 0])?;
+
+        // Image descriptor covering the whole screen, no local colour table.
+        self.out.write_all(&[0x2C, 0, 0, 0, 0])?;
+        self.out.write_all(&(w as u16).to_le_bytes())?;
+        self.out.write_all(&(h as u16).to_le_bytes())?;
+        self.out.write_all(&[0])?;
+
+        let indices: Vec<u8> = frame
+            .pixels(self.scale)
+            .map(|l| self.ramp.index(l))
+            .collect();
+        self.out.write_all(&[self.bits])?;
+        for block in lzw_encode(self.bits, &indices).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0])
+    }
+
+    /// Write the trailer and hand back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&[0x3B])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Packs codes of varying width into bytes, least significant bit first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    filled: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u8) {
+        self.buffer |= (code as u32) << self.filled;
+        self.filled += width;
+        while self.filled >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.filled -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Largest code GIF's LZW allows.
+const MAX_CODE: u16 = 4095;
+
+/// Compress palette indices of `min_code_size` bits with GIF's variant of LZW.
+fn lzw_encode(min_code_size: u8, indices: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut bits = BitWriter {
+        bytes: vec![],
+        buffer: 0,
+        filled: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut width = min_code_size + 1;
+    bits.write(clear, width);
+
+    let mut prefix = match indices.first() {
+        Some(&first) => first as u16,
+        None => {
+            bits.write(end, width);
+            return bits.finish();
+        }
+    };
+    for &index in indices[1..].iter() {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+
+        bits.write(prefix, width);
+        // The decoder adds its entries one code late, so widen once the
+        // previous entry no longer fits.
+        if next_code > (1 << width) - 1 && width < 12 {
+            width += 1;
+        }
+        if next_code <= MAX_CODE {
+            table.insert((prefix, index), next_code);
+            next_code += 1;
+        } else {
+            bits.write(clear, width);
+            table.clear();
+            next_code = end + 1;
+            width = min_code_size + 1;
+        }
+        prefix = index as u16;
+    }
+    bits.write(prefix, width);
+    if next_code > (1 << width) - 1 && width < 12 {
+        width += 1;
+    }
+    bits.write(end, width);
+    bits.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A straightforward GIF LZW decoder to check the encoder against.
+    fn lzw_decode(min_code_size: u8, data: &[u8]) -> Vec<u8> {
+        let clear = 1u16 << min_code_size;
+        let end = clear + 1;
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            *table = (0..clear).map(|i| vec![i as u8]).collect();
+            table.push(vec![]);
+            table.push(vec![]);
+        };
+        let mut table = vec![];
+        reset(&mut table);
+        let mut width = min_code_size + 1;
+        let (mut pos, mut out) = (0usize, vec![]);
+        let mut previous: Option<Vec<u8>> = None;
+
+        loop {
+            let mut code = 0u16;
+            for b in 0..width as usize {
+                let bit = (data[(pos + b) / 8] >> ((pos + b) % 8)) & 1;
+                code |= (bit as u16) << b;
+            }
+            pos += width as usize;
+
+            if code == clear {
+                reset(&mut table);
+                width = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(prev)) => {
+                    let mut entry = prev.clone();
+                    entry.push(prev[0]);
+                    entry
+                }
+                (None, None) => panic!("Unknown code {}.", code),
+            };
+            if let Some(mut prev) = previous {
+                prev.push(entry[0]);
+                if table.len() <= MAX_CODE as usize {
+                    table.push(prev);
+                }
+            }
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+            out.extend(entry.iter());
+            previous = Some(entry);
+        }
+    }
+
+    /// Decode every image of a GIF written by `GifWriter`.
+    fn gif_frames(gif: &[u8]) -> (usize, usize, Vec<Vec<u8>>) {
+        assert_eq!(&gif[..6], b"GIF89a");
+        let w = u16::from_le_bytes([gif[6], gif[7]]) as usize;
+        let h = u16::from_le_bytes([gif[8], gif[9]]) as usize;
+        let table = 3 << ((gif[10] & 7) + 1);
+        let mut pos = 13 + table;
+        let mut frames = vec![];
+        loop {
+            match gif[pos] {
+                0x21 => {
+                    pos += 2;
+                    while gif[pos] != 0 {
+                        pos += gif[pos] as usize + 1;
+                    }
+                    pos += 1;
+                }
+                0x2C => {
+                    pos += 10;
+                    let min_code_size = gif[pos];
+                    pos += 1;
+                    let mut data = vec![];
+                    while gif[pos] != 0 {
+                        let len = gif[pos] as usize;
+                        data.extend_from_slice(&gif[pos + 1..pos + 1 + len]);
+                        pos += len + 1;
+                    }
+                    pos += 1;
+                    frames.push(lzw_decode(min_code_size, &data));
+                }
+                0x3B => return (w, h, frames),
+                b => panic!("Unexpected block {:#x}.", b),
+            }
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let cases: Vec<(u8, Vec<u8>)> = vec![
+            (2, vec![]),
+            (2, vec![1]),
+            (2, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            (2, (0..1000).map(|i| (i % 4) as u8).collect()),
+            // Enough noise to fill the table and force clear codes.
+            (
+                8,
+                (0..100_000u32)
+                    .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+                    .collect(),
+            ),
+            (4, (0..50_000u32).map(|i| ((i * i) % 13) as u8).collect()),
+        ];
+        for (min_code_size, indices) in cases {
+            let encoded = lzw_encode(min_code_size, &indices);
+            assert_eq!(lzw_decode(min_code_size, &encoded), indices);
+        }
+    }
+
+    #[test]
+    fn test_ramp() {
+        let ramp = Ramp::grayscale(3);
+        assert_eq!(
+            ramp.colors,
+            vec![[0, 0, 0], [128, 128, 128], [255, 255, 255]]
+        );
+        assert_eq!(ramp.color(7), [255, 255, 255]);
+        assert_eq!(Ramp::heat(10).len(), 10);
+        assert_eq!(Ramp::heat(4).colors[2], [255, 200, 0]);
+    }
+
+    #[test]
+    fn test_pgm_ppm() {
+        let frame = Frame::from_fn(2, 1, |_, j| j as u8 * 9);
+
+        let mut out = vec![];
+        write_pgm(&mut out, &frame, 9, 2).unwrap();
+        assert_eq!(out, b"P5\n4 2\n9\n\x00\x00\x09\x09\x00\x00\x09\x09");
+
+        let mut out = vec![];
+        write_ppm(&mut out, &frame, &Ramp::grayscale(10), 1).unwrap();
+        assert_eq!(out, b"P6\n2 1\n255\n\x00\x00\x00\xff\xff\xff");
+
+        let frame = Frame::from_fn(9, 1, |_, j| (j % 2) as u8);
+        let mut out = vec![];
+        write_pbm(&mut out, &frame, 1).unwrap();
+        assert_eq!(out, b"P4\n9 1\n\x55\x00");
+    }
+
+    #[test]
+    fn test_gif() {
+        let frames: Vec<Frame> = (0..3)
+            .map(|t| Frame::from_fn(3, 2, move |i, j| ((i + j + t) % 10) as u8))
+            .collect();
+        let mut gif = GifWriter::new(vec![], 3, 2, 2, Ramp::heat(10)).unwrap();
+        for frame in frames.iter() {
+            gif.add_frame(frame, 10).unwrap();
+        }
+        let gif = gif.finish().unwrap();
+
+        let (w, h, decoded) = gif_frames(&gif);
+        assert_eq!((w, h), (6, 4));
+        assert_eq!(decoded.len(), 3);
+        for (frame, pixels) in frames.iter().zip(decoded) {
+            assert_eq!(frame.pixels(2).collect::<Vec<_>>(), pixels);
+        }
+    }
+}
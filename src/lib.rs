@@ -1,12 +1,18 @@
+mod bigint;
 mod day03;
+mod day06;
 mod day08;
+mod day10;
 mod day11;
 mod day12;
+mod day13;
 mod day14;
 mod day15;
 mod day16;
 mod day17;
 // mod day18;
+mod export;
+mod ocr;
 
 pub trait ReadStr: Sized {
     type Err;
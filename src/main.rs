@@ -1,5 +1,6 @@
 mod day03;
 mod day11;
+mod export;
 
 fn main() {
     println!("Day 03");
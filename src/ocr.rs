@@ -0,0 +1,240 @@
+#![allow(dead_code)]
+//! Read answers that a puzzle draws as capital letters in the 4 x 6 AoC font.
+//!
+//! Letters are separated by at least one blank column, so glyphs are found by
+//! splitting the lit cells into runs of non-blank columns and comparing each
+//! run with the font after trimming blank columns.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    // The only letter that is five columns wide.
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// A glyph that is not in the font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownGlyph {
+    /// Position of the glyph in the text.
+    pub index: usize,
+    /// Leftmost column of the glyph in the input coordinates.
+    pub column: isize,
+    /// The glyph drawn with `#` and `.`, one row per line.
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    Empty,
+    WrongHeight(usize),
+    /// The text read so far, with `?` for each glyph in `glyphs`.
+    UnknownGlyphs {
+        text: String,
+        glyphs: Vec<UnknownGlyph>,
+    },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::Empty => write!(f, "Nothing to read."),
+            OcrError::WrongHeight(h) => write!(
+                f,
+                "Text is {} rows high, letters are {} rows high.",
+                h, GLYPH_HEIGHT
+            ),
+            OcrError::UnknownGlyphs { text, glyphs } => {
+                write!(f, "Read '{}' with {} unknown glyph(s):", text, glyphs.len())?;
+                for glyph in glyphs {
+                    write!(
+                        f,
+                        "\nglyph {} at column {}:\n{}",
+                        glyph.index, glyph.column, glyph.pattern
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+fn trimmed(rows: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let used = |j: usize| rows.iter().any(|row| row.get(j).copied().unwrap_or(false));
+    let first = (0..width).find(|&j| used(j)).unwrap_or(width);
+    let last = (0..width).rev().find(|&j| used(j)).map_or(first, |j| j + 1);
+    rows.iter()
+        .map(|row| {
+            (first..last)
+                .map(|j| row.get(j).copied().unwrap_or(false))
+                .collect()
+        })
+        .collect()
+}
+
+fn letter(glyph: &[Vec<bool>]) -> Option<char> {
+    FONT.iter()
+        .find(|(_, rows)| {
+            let rows: Vec<Vec<bool>> = rows
+                .iter()
+                .map(|r| r.chars().map(|c| c == '#').collect())
+                .collect();
+            trimmed(&rows) == glyph
+        })
+        .map(|(c, _)| *c)
+}
+
+fn pattern(glyph: &[Vec<bool>]) -> String {
+    glyph
+        .iter()
+        .map(|row| row.iter().map(|&on| if on { '#' } else { '.' }).collect())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Reads the letters drawn by lit cells `(x, y)`, with `x` the column.
+pub fn read_points<I: IntoIterator<Item = (isize, isize)>>(points: I) -> Result<String, OcrError> {
+    let points: HashSet<(isize, isize)> = points.into_iter().collect();
+    let x_min = points.iter().map(|p| p.0).min().ok_or(OcrError::Empty)?;
+    let x_max = points.iter().map(|p| p.0).max().ok_or(OcrError::Empty)?;
+    let y_min = points.iter().map(|p| p.1).min().ok_or(OcrError::Empty)?;
+    let y_max = points.iter().map(|p| p.1).max().ok_or(OcrError::Empty)?;
+
+    let height = (y_max - y_min + 1) as usize;
+    if height != GLYPH_HEIGHT {
+        return Err(OcrError::WrongHeight(height));
+    }
+
+    let column =
+        |x: isize| -> Vec<bool> { (y_min..=y_max).map(|y| points.contains(&(x, y))).collect() };
+
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    let mut x = x_min;
+    while x <= x_max {
+        let start = x;
+        let mut columns = Vec::new();
+        while x <= x_max && column(x).contains(&true) {
+            columns.push(column(x));
+            x += 1;
+        }
+        // Skip the blank columns between letters.
+        while x <= x_max && !column(x).contains(&true) {
+            x += 1;
+        }
+
+        let glyph: Vec<Vec<bool>> = (0..GLYPH_HEIGHT)
+            .map(|i| columns.iter().map(|col| col[i]).collect())
+            .collect();
+        match letter(&glyph) {
+            Some(c) => text.push(c),
+            None => {
+                unknown.push(UnknownGlyph {
+                    index: text.chars().count(),
+                    column: start,
+                    pattern: pattern(&glyph),
+                });
+                text.push('?');
+            }
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnknownGlyphs {
+            text,
+            glyphs: unknown,
+        })
+    }
+}
+
+/// Reads letters drawn as text, with `on` marking lit cells.
+pub fn read_str(s: &str, on: char) -> Result<String, OcrError> {
+    read_points(s.lines().enumerate().flat_map(|(y, line)| {
+        line.chars()
+            .enumerate()
+            .filter(move |&(_, c)| c == on)
+            .map(move |(x, _)| (x as isize, y as isize))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(text: &str) -> String {
+        let glyphs: Vec<&[&str; GLYPH_HEIGHT]> = text
+            .chars()
+            .map(|c| &FONT.iter().find(|(l, _)| *l == c).unwrap().1)
+            .collect();
+        (0..GLYPH_HEIGHT)
+            .map(|i| glyphs.iter().map(|g| format!("{}.", g[i])).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_font() {
+        let letters: String = FONT.iter().map(|(c, _)| *c).collect();
+        assert_eq!(read_str(&draw(&letters), '#').unwrap(), letters);
+    }
+
+    #[test]
+    fn test_read_points() {
+        // Leading 'I' has a blank first column, shifted coordinates are fine.
+        let points = draw("IRGZ")
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.char_indices()
+                    .filter(|&(_, c)| c == '#')
+                    .map(move |(x, _)| (x as isize - 20, y as isize - 3))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(read_points(points).unwrap(), "IRGZ");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(read_str("", '#'), Err(OcrError::Empty));
+        assert_eq!(read_str("#\n#", '#'), Err(OcrError::WrongHeight(2)));
+
+        let text = "#..#.####\n#..#.#..#\n####.#..#\n#..#.#..#\n#..#.#..#\n#..#.####";
+        match read_str(text, '#') {
+            Err(OcrError::UnknownGlyphs { text, glyphs }) => {
+                assert_eq!(text, "H?");
+                assert_eq!(glyphs.len(), 1);
+                assert_eq!(glyphs[0].index, 1);
+                assert_eq!(glyphs[0].column, 5);
+                assert_eq!(glyphs[0].pattern.lines().next(), Some("####"));
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+}